@@ -1,4 +1,4 @@
-use cgmath::{Vector2, vec2};
+use vector::{Vector2, vec2};
 use physics_num::PhysicsNum;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -46,12 +46,31 @@ impl<N: PhysicsNum> Aabb<N> {
     pub fn size(&self) -> Vector2<N> {
         self.size
     }
+    pub fn top_left(&self) -> Vector2<N> {
+        self.top_left
+    }
+    pub fn corners(&self) -> [Vector2<N>; 4] {
+        let bottom_right = self.bottom_right_coord();
+        [
+            self.top_left,
+            vec2(bottom_right.x, self.top_left.y),
+            bottom_right,
+            vec2(self.top_left.x, bottom_right.y),
+        ]
+    }
     pub fn is_intersecting(&self, other: &Aabb<N>) -> bool {
         self.top_left.x + self.size.x >= other.top_left.x
             && other.top_left.x + other.size.x >= self.top_left.x
             && self.top_left.y + self.size.y >= other.top_left.y
             && other.top_left.y + other.size.y >= self.top_left.y
     }
+    /// Like `is_intersecting`, but aabbs which only touch don't overlap.
+    pub fn is_overlapping(&self, other: &Aabb<N>) -> bool {
+        self.top_left.x + self.size.x > other.top_left.x
+            && other.top_left.x + other.size.x > self.top_left.x
+            && self.top_left.y + self.size.y > other.top_left.y
+            && other.top_left.y + other.size.y > self.top_left.y
+    }
     pub fn centre(&self) -> Vector2<N> {
         self.top_left + self.size / PhysicsNum::two()
     }
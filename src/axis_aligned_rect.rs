@@ -1,6 +1,6 @@
 use aabb::Aabb;
 use physics_num::PhysicsNum;
-use cgmath::{Vector2, vec2};
+use vector::{Vector2, vec2};
 use line_segment::LineSegment;
 use num::Zero;
 use shape::Collide;
@@ -0,0 +1,44 @@
+/// Keeps whichever inserted value has the best key so far.
+#[derive(Debug, Clone)]
+pub struct BestMap<K, V> {
+    best: Option<(K, V)>,
+}
+
+impl<K: PartialOrd, V> BestMap<K, V> {
+    pub fn new() -> Self {
+        Self { best: None }
+    }
+    /// Keeps the value if its key is at most the best key so far, so with equal keys the
+    /// last one inserted wins.
+    pub fn insert_le(&mut self, key: K, value: V) {
+        let better = match self.best {
+            Some((ref best_key, _)) => key <= *best_key,
+            None => true,
+        };
+        if better {
+            self.best = Some((key, value));
+        }
+    }
+    #[cfg(feature = "std")]
+    pub fn into_value(self) -> Option<V> {
+        self.best.map(|(_, value)| value)
+    }
+    pub fn into_key_and_value(self) -> Option<(K, V)> {
+        self.best
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn insert_le() {
+        let mut best = BestMap::new();
+        best.insert_le(3, 'a');
+        best.insert_le(1, 'b');
+        best.insert_le(2, 'c');
+        best.insert_le(1, 'd');
+        assert_eq!(best.into_key_and_value(), Some((1, 'd')));
+    }
+}
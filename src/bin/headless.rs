@@ -0,0 +1,70 @@
+extern crate integer_physics;
+
+use integer_physics::svg::{self, SvgOptions};
+use integer_physics::headless::{self, Format, InputScript};
+use integer_physics::{load_any, vec2, GameState};
+use std::env;
+use std::io;
+
+const USAGE: &str = "usage: headless LEVEL [--inputs PATH] [--ticks N] [--format csv|json] \
+                     [--png PATH] [--svg PATH] [--svg-quad-tree] [--svg-velocity]";
+
+fn main() {
+    let mut level_path = None;
+    let mut inputs_path = None;
+    let mut num_ticks = None;
+    let mut format = Format::Csv;
+    let mut png_path = None;
+    let mut svg_path = None;
+    let mut svg_options = SvgOptions::default();
+    let mut env_args = env::args().skip(1);
+    while let Some(arg) = env_args.next() {
+        if arg == "--inputs" {
+            inputs_path = Some(env_args.next().expect("--inputs requires a path"));
+        } else if arg == "--ticks" {
+            let ticks = env_args.next().expect("--ticks requires a number");
+            num_ticks = Some(ticks.parse::<u32>().expect("--ticks requires a number"));
+        } else if arg == "--format" {
+            let name = env_args.next().expect("--format requires csv or json");
+            format = name.parse().unwrap_or_else(|e: String| panic!("{}", e));
+        } else if arg == "--png" {
+            png_path = Some(env_args.next().expect("--png requires a path"));
+        } else if arg == "--svg" {
+            svg_path = Some(env_args.next().expect("--svg requires a path"));
+        } else if arg == "--svg-quad-tree" {
+            svg_options.quad_tree = true;
+        } else if arg == "--svg-velocity" {
+            svg_options.velocity = true;
+        } else if arg.starts_with("--") || level_path.is_some() {
+            panic!("Unexpected argument: {}\n{}", arg, USAGE);
+        } else {
+            level_path = Some(arg);
+        }
+    }
+    let level_path = level_path.expect(USAGE);
+    let level = load_any(&level_path)
+        .unwrap_or_else(|e| panic!("Failed to load level {}: {}", level_path, e));
+    let script = inputs_path.as_ref().map_or_else(InputScript::default, |path| {
+        InputScript::load(path).unwrap_or_else(|e| panic!("Failed to load inputs {}: {}", path, e))
+    });
+    let mut game_state = GameState::new(vec2(level.size[0] as f32, level.size[1] as f32));
+    game_state.load_level(&level).expect("Invalid level");
+    let stdout = io::stdout();
+    headless::run(
+        &mut game_state,
+        &script,
+        num_ticks.unwrap_or_else(|| script.num_ticks()),
+        format,
+        stdout.lock(),
+    ).expect("Failed to write output");
+    if let Some(png_path) = png_path {
+        headless::capture(&game_state, level.size[0] as u32, level.size[1] as u32)
+            .save_png(&png_path)
+            .unwrap_or_else(|e| panic!("Failed to save {}: {}", png_path, e));
+    }
+    if let Some(svg_path) = svg_path {
+        svg::export(&game_state, &svg_options)
+            .save(&svg_path)
+            .unwrap_or_else(|e| panic!("Failed to save {}: {}", svg_path, e));
+    }
+}
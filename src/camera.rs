@@ -0,0 +1,139 @@
+use vector::{Vector2, vec2};
+
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 8.;
+const DEFAULT_DEAD_ZONE: [f32; 2] = [128., 96.];
+/// The fraction of the distance to its target which the camera moves each tick.
+const DEFAULT_FOLLOW_RATE: f32 = 0.1;
+
+fn clamp_axis(centre: f32, half_visible: f32, min: f32, max: f32) -> f32 {
+    if max - min <= half_visible * 2. {
+        (min + max) / 2.
+    } else {
+        centre.max(min + half_visible).min(max - half_visible)
+    }
+}
+
+/// The part of the world which is shown, in world pixels. `view_size` is how much of the
+/// world is visible at a zoom of 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    centre: Vector2<f32>,
+    zoom: f32,
+    view_size: Vector2<f32>,
+    bounds: Option<(Vector2<f32>, Vector2<f32>)>,
+    dead_zone: Vector2<f32>,
+    follow_rate: f32,
+}
+
+impl Camera {
+    pub fn new(view_size: Vector2<f32>) -> Self {
+        Self {
+            centre: view_size / 2.,
+            zoom: 1.,
+            view_size,
+            bounds: None,
+            dead_zone: DEFAULT_DEAD_ZONE.into(),
+            follow_rate: DEFAULT_FOLLOW_RATE,
+        }
+    }
+    pub fn centre(&self) -> Vector2<f32> {
+        self.centre
+    }
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+    pub fn visible_size(&self) -> Vector2<f32> {
+        self.view_size / self.zoom
+    }
+    pub fn top_left(&self) -> Vector2<f32> {
+        self.centre - self.visible_size() / 2.
+    }
+    pub fn set_view_size(&mut self, view_size: Vector2<f32>) {
+        self.view_size = view_size;
+        self.clamp();
+    }
+    /// The camera keeps everything it shows inside `top_left..top_left + size`, unless the
+    /// bounds are smaller than the view, in which case it centres on them.
+    pub fn set_bounds(&mut self, top_left: Vector2<f32>, size: Vector2<f32>) {
+        self.bounds = Some((top_left, top_left + size));
+        self.clamp();
+    }
+    /// Following only moves the camera once its target leaves a box this size in the
+    /// middle of the view.
+    pub fn set_dead_zone(&mut self, dead_zone: Vector2<f32>) {
+        self.dead_zone = dead_zone;
+    }
+    pub fn set_follow_rate(&mut self, follow_rate: f32) {
+        self.follow_rate = follow_rate;
+    }
+    pub fn look_at(&mut self, centre: Vector2<f32>) {
+        self.centre = centre;
+        self.clamp();
+    }
+    pub fn pan(&mut self, offset: Vector2<f32>) {
+        self.centre += offset;
+        self.clamp();
+    }
+    pub fn zoom_by(&mut self, factor: f32) {
+        self.zoom = (self.zoom * factor).max(MIN_ZOOM).min(MAX_ZOOM);
+        self.clamp();
+    }
+    /// Moves towards `target` as far as following it for `ticks` ticks would, so it can
+    /// be called once per frame with the interpolated position of what it follows.
+    pub fn follow(&mut self, target: Vector2<f32>, ticks: f32) {
+        let half_dead_zone = self.dead_zone / 2.;
+        let to_target = target - self.centre;
+        let outside = |offset: f32, half: f32| offset - offset.max(-half).min(half);
+        let outside_dead_zone = vec2(
+            outside(to_target.x, half_dead_zone.x),
+            outside(to_target.y, half_dead_zone.y),
+        );
+        let rate = 1. - (1. - self.follow_rate).powf(ticks);
+        self.centre += outside_dead_zone * rate;
+        self.clamp();
+    }
+    fn clamp(&mut self) {
+        if let Some((min, max)) = self.bounds {
+            let half_visible = self.visible_size() / 2.;
+            self.centre = vec2(
+                clamp_axis(self.centre.x, half_visible.x, min.x, max.x),
+                clamp_axis(self.centre.y, half_visible.y, min.y, max.y),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn follow_with_dead_zone() {
+        let mut camera = Camera::new(vec2(960., 640.));
+        camera.set_follow_rate(0.5);
+        camera.follow(vec2(480. + 60., 320.), 1.);
+        assert_eq!(camera.centre(), vec2(480., 320.));
+        camera.follow(vec2(480. + 164., 320. - 48.), 1.);
+        assert_eq!(camera.centre(), vec2(530., 320.));
+        // two ticks at once go as far as two single ticks
+        camera.follow(vec2(530. + 164., 320.), 2.);
+        assert_eq!(camera.centre(), vec2(605., 320.));
+    }
+
+    #[test]
+    fn bounds() {
+        let mut camera = Camera::new(vec2(960., 640.));
+        camera.set_bounds(vec2(0., 0.), vec2(2000., 500.));
+        // narrower than the view vertically, so centred
+        assert_eq!(camera.centre(), vec2(480., 250.));
+        camera.pan(vec2(-100., 0.));
+        assert_eq!(camera.top_left(), vec2(0., -70.));
+        camera.look_at(vec2(5000., 0.));
+        assert_eq!(camera.top_left().x, 2000. - 960.);
+        camera.zoom_by(2.);
+        assert_eq!(camera.visible_size(), vec2(480., 320.));
+        // zooming keeps the centre where it was
+        assert_eq!(camera.top_left(), vec2(1520. - 240., 90.));
+    }
+}
@@ -1,5 +1,5 @@
 use physics_num::{self, PhysicsNum};
-use cgmath::{Vector2, vec2};
+use vector::{Vector2, vec2};
 use line_segment::LineSegment;
 use num::{One, Zero};
 
@@ -120,7 +120,7 @@ pub fn vertex_moving_towards_edge<N: PhysicsNum>(
 #[cfg(test)]
 mod test {
     use super::*;
-    use cgmath::vec2;
+    use vector::vec2;
 
     fn v(x: i64, y: i64) -> Vector2<i64> {
         vec2(x, y)
@@ -0,0 +1,196 @@
+use vector::Vector2;
+use fnv::{FnvHashMap, FnvHashSet};
+use game::EntityId;
+use line_segment::LineSegment;
+use pixel_num::SubPixelI64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContactPhase {
+    Began,
+    Persisted,
+    Ended,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CollisionEvent {
+    pub phase: ContactPhase,
+    pub moving_id: EntityId,
+    pub other_id: EntityId,
+    pub edge: LineSegment<SubPixelI64>,
+    pub contact_point: Vector2<SubPixelI64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensorPhase {
+    Entered,
+    Exited,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SensorEvent {
+    pub phase: SensorPhase,
+    pub sensor_id: EntityId,
+    pub entity_id: EntityId,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Contact {
+    moving_id: EntityId,
+    other_id: EntityId,
+    edge: LineSegment<SubPixelI64>,
+    contact_point: Vector2<SubPixelI64>,
+}
+
+impl Contact {
+    fn event(&self, phase: ContactPhase) -> CollisionEvent {
+        CollisionEvent {
+            phase,
+            moving_id: self.moving_id,
+            other_id: self.other_id,
+            edge: self.edge,
+            contact_point: self.contact_point,
+        }
+    }
+}
+
+fn pair_key(a: EntityId, b: EntityId) -> (EntityId, EntityId) {
+    if a < b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// Two entities are touching during a tick if either one collided with the other while
+/// moving, or if they're adjacent at the end of the tick. Comparing the touching pairs of
+/// consecutive ticks gives the phase of each contact.
+#[derive(Default, Debug, Clone)]
+pub struct ContactTracker {
+    previous: FnvHashMap<(EntityId, EntityId), Contact>,
+    current: FnvHashMap<(EntityId, EntityId), Contact>,
+}
+
+impl ContactTracker {
+    pub fn record(
+        &mut self,
+        moving_id: EntityId,
+        other_id: EntityId,
+        edge: LineSegment<SubPixelI64>,
+        contact_point: Vector2<SubPixelI64>,
+    ) {
+        self.current.insert(
+            pair_key(moving_id, other_id),
+            Contact {
+                moving_id,
+                other_id,
+                edge,
+                contact_point,
+            },
+        );
+    }
+    pub fn is_touching(&self, a: EntityId, b: EntityId) -> bool {
+        self.current.contains_key(&pair_key(a, b))
+    }
+    pub fn end_tick(&mut self, events: &mut Vec<CollisionEvent>) {
+        let start = events.len();
+        for (key, contact) in self.current.iter() {
+            let phase = if self.previous.contains_key(key) {
+                ContactPhase::Persisted
+            } else {
+                ContactPhase::Began
+            };
+            events.push(contact.event(phase));
+        }
+        for (key, contact) in self.previous.iter() {
+            if !self.current.contains_key(key) {
+                events.push(contact.event(ContactPhase::Ended));
+            }
+        }
+        events[start..].sort_by_key(|event| pair_key(event.moving_id, event.other_id));
+        ::std::mem::swap(&mut self.previous, &mut self.current);
+        self.current.clear();
+    }
+    pub fn clear(&mut self) {
+        self.previous.clear();
+        self.current.clear();
+    }
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct SensorTracker {
+    previous: FnvHashSet<(EntityId, EntityId)>,
+    current: FnvHashSet<(EntityId, EntityId)>,
+}
+
+impl SensorTracker {
+    pub fn record(&mut self, sensor_id: EntityId, entity_id: EntityId) {
+        self.current.insert((sensor_id, entity_id));
+    }
+    pub fn end_tick(&mut self, events: &mut Vec<SensorEvent>) {
+        let start = events.len();
+        for &(sensor_id, entity_id) in self.current.difference(&self.previous) {
+            events.push(SensorEvent {
+                phase: SensorPhase::Entered,
+                sensor_id,
+                entity_id,
+            });
+        }
+        for &(sensor_id, entity_id) in self.previous.difference(&self.current) {
+            events.push(SensorEvent {
+                phase: SensorPhase::Exited,
+                sensor_id,
+                entity_id,
+            });
+        }
+        events[start..].sort_by_key(|event| (event.sensor_id, event.entity_id));
+        ::std::mem::swap(&mut self.previous, &mut self.current);
+        self.current.clear();
+    }
+    pub fn clear(&mut self) {
+        self.previous.clear();
+        self.current.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn phases(events: &[SensorEvent]) -> Vec<(SensorPhase, EntityId, EntityId)> {
+        events
+            .iter()
+            .map(|event| (event.phase, event.sensor_id, event.entity_id))
+            .collect()
+    }
+
+    #[test]
+    fn sensor_events_ordered_by_sensor_then_entity() {
+        let mut tracker = SensorTracker::default();
+        let mut events = Vec::new();
+        tracker.record(2, 1);
+        tracker.record(1, 3);
+        tracker.record(1, 2);
+        tracker.end_tick(&mut events);
+        let entered = vec![
+            (SensorPhase::Entered, 1, 2),
+            (SensorPhase::Entered, 1, 3),
+            (SensorPhase::Entered, 2, 1),
+        ];
+        assert_eq!(phases(&events), entered);
+        // the next tick's events are appended, with entries and exits sorted together
+        tracker.record(3, 0);
+        tracker.record(2, 1);
+        tracker.record(0, 4);
+        tracker.end_tick(&mut events);
+        assert_eq!(
+            phases(&events[3..]),
+            vec![
+                (SensorPhase::Entered, 0, 4),
+                (SensorPhase::Exited, 1, 2),
+                (SensorPhase::Exited, 1, 3),
+                (SensorPhase::Entered, 3, 0),
+            ]
+        );
+        assert_eq!(phases(&events[..3]), entered);
+    }
+}
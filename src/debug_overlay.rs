@@ -0,0 +1,182 @@
+//! Draws the physics internals on top of the world, entirely with lines. Collisions and
+//! swept aabbs are only available after an update with `GameState::set_movement_debug`
+//! enabled.
+
+use vector::Vector2;
+use aabb::Aabb;
+use draw::{DEFAULT_LINE_THICKNESS, DrawTarget};
+use game::GameState;
+use pixel_num::sub_pixel_i64::{SubPixelI64, vector_to_f32_pixel};
+
+const AABB_COLOUR: [f32; 4] = [0., 1., 1., 1.];
+const SWEPT_COLOUR: [f32; 4] = [1., 0., 1., 1.];
+const QUAD_TREE_COLOUR: [f32; 4] = [0.5, 0.5, 0.5, 1.];
+const LOOSE_BOUNDS_COLOUR: [f32; 4] = [0.5, 0.5, 0.5, 0.5];
+const COLLISION_EDGE_COLOUR: [f32; 4] = [1., 0.2, 0.2, 1.];
+const ALLOWED_MOVEMENT_COLOUR: [f32; 4] = [1., 1., 0., 1.];
+const VELOCITY_COLOUR: [f32; 4] = [1., 1., 1., 1.];
+const OUTLINE_THICKNESS: f32 = 1.;
+/// Movement per tick is too short to see, so arrows show this many ticks of it.
+const ARROW_TICKS: f32 = 8.;
+
+fn aabb_outline<D: DrawTarget + ?Sized>(
+    aabb: &Aabb<SubPixelI64>,
+    colour: [f32; 4],
+    target: &mut D,
+) {
+    target.rect_outline(
+        vector_to_f32_pixel(aabb.top_left()),
+        vector_to_f32_pixel(aabb.size()),
+        OUTLINE_THICKNESS,
+        colour,
+    );
+}
+
+fn scaled_arrow<D: DrawTarget + ?Sized>(
+    start: Vector2<SubPixelI64>,
+    movement: Vector2<SubPixelI64>,
+    colour: [f32; 4],
+    target: &mut D,
+) {
+    let start = vector_to_f32_pixel(start);
+    target.arrow(
+        start,
+        start + vector_to_f32_pixel(movement) * ARROW_TICKS,
+        DEFAULT_LINE_THICKNESS,
+        colour,
+    );
+}
+
+pub fn draw_aabbs<D: DrawTarget + ?Sized>(game_state: &GameState, target: &mut D) {
+    for (id, _) in game_state.positions() {
+        if let Some(aabb) = game_state.aabb(id) {
+            aabb_outline(&aabb, AABB_COLOUR, target);
+        }
+    }
+}
+
+pub fn draw_swept_aabbs<D: DrawTarget + ?Sized>(game_state: &GameState, target: &mut D) {
+    for step in game_state.movement_debug() {
+        aabb_outline(&step.swept, SWEPT_COLOUR, target);
+    }
+}
+
+/// Node cells, plus the loose bounds of nodes which hold anything, since items can
+/// overhang their node's cell by up to half its size on each side.
+pub fn draw_quad_tree<D: DrawTarget + ?Sized>(game_state: &GameState, target: &mut D) {
+    game_state.for_each_quad_tree_node(|aabb, num_items| {
+        if num_items > 0 {
+            aabb_outline(&aabb.double_about_centre(), LOOSE_BOUNDS_COLOUR, target);
+        }
+        aabb_outline(aabb, QUAD_TREE_COLOUR, target);
+    });
+}
+
+/// The edge hit by each colliding step, and the movement allowed before hitting it.
+pub fn draw_collisions<D: DrawTarget + ?Sized>(game_state: &GameState, target: &mut D) {
+    for step in game_state.movement_debug() {
+        if let Some(ref collision) = step.collision {
+            target.line_segment(
+                vector_to_f32_pixel(collision.line_segment.start),
+                vector_to_f32_pixel(collision.line_segment.end),
+                DEFAULT_LINE_THICKNESS,
+                COLLISION_EDGE_COLOUR,
+            );
+            scaled_arrow(
+                step.start.centre(),
+                collision.allowed_movement,
+                ALLOWED_MOVEMENT_COLOUR,
+                target,
+            );
+        }
+    }
+}
+
+pub fn draw_velocities<D: DrawTarget + ?Sized>(game_state: &GameState, target: &mut D) {
+    for (id, velocity) in game_state.velocities() {
+        if let Some(aabb) = game_state.aabb(id) {
+            scaled_arrow(aabb.centre(), velocity, VELOCITY_COLOUR, target);
+        }
+    }
+}
+
+pub fn draw<D: DrawTarget + ?Sized>(game_state: &GameState, target: &mut D) {
+    draw_quad_tree(game_state, target);
+    draw_aabbs(game_state, target);
+    draw_swept_aabbs(game_state, target);
+    draw_collisions(game_state, target);
+    draw_velocities(game_state, target);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use vector::vec2;
+    use game::InputModel;
+
+    #[derive(Default)]
+    struct Lines {
+        lines: Vec<([f32; 2], [f32; 2], [f32; 4])>,
+    }
+
+    impl DrawTarget for Lines {
+        fn axis_aligned_rect(&mut self, _: Vector2<f32>, _: Vector2<f32>, _: [f32; 4]) {
+            panic!("The overlay should only draw lines");
+        }
+        fn line_segment(
+            &mut self,
+            start: Vector2<f32>,
+            end: Vector2<f32>,
+            _: f32,
+            colour: [f32; 4],
+        ) {
+            self.lines.push((start.into(), end.into(), colour));
+        }
+        fn convex_polygon(&mut self, _: &[Vector2<f32>], _: [f32; 4]) {
+            panic!("The overlay should only draw lines");
+        }
+        fn circle(&mut self, _: Vector2<f32>, _: f32, _: [f32; 4]) {
+            panic!("The overlay should only draw lines");
+        }
+    }
+
+    #[test]
+    fn player_landing_on_floor() {
+        let mut game_state = GameState::new(vec2(960., 640.));
+        game_state.init_demo();
+        game_state.set_movement_debug(true);
+        let mut input_model = InputModel::default();
+        input_model.set_down(1.);
+        // the player starts 86 pixels above the floor and falls 4 pixels a tick
+        for _ in 0..22 {
+            game_state.update(&input_model);
+        }
+        let player_aabb = game_state.aabb(0).unwrap();
+        let step = game_state
+            .movement_debug()
+            .iter()
+            .find(|step| step.id == 0)
+            .expect("Expected the player to move");
+        let collision = step.collision.as_ref().expect("Expected a collision");
+        // it stops just short of the floor, 2 pixels down
+        let allowed_movement = vector_to_f32_pixel(collision.allowed_movement);
+        assert_eq!(allowed_movement.x, 0.);
+        assert!(allowed_movement.y > 1.9 && allowed_movement.y <= 2.);
+        let player_top_left = vector_to_f32_pixel(player_aabb.top_left());
+
+        let mut lines = Lines::default();
+        draw(&game_state, &mut lines);
+        let has_line = |start: [f32; 2], end: [f32; 2], colour| {
+            lines.lines.iter().any(|&line| line == (start, end, colour))
+        };
+        assert!(has_line([50., 200.], [450., 200.], COLLISION_EDGE_COLOUR));
+        let allowed_end = vec2(216., 166.) + allowed_movement * ARROW_TICKS;
+        assert!(has_line([216., 166.], allowed_end.into(), ALLOWED_MOVEMENT_COLOUR));
+        let player_top_right = player_top_left + vec2(32., 0.);
+        assert!(has_line(player_top_left.into(), player_top_right.into(), AABB_COLOUR));
+        assert!(has_line([0., 0.], [960., 0.], QUAD_TREE_COLOUR));
+
+        game_state.set_movement_debug(false);
+        assert!(game_state.movement_debug().is_empty());
+    }
+}
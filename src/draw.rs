@@ -0,0 +1,93 @@
+use vector::{Vector2, vec2};
+
+const ARROW_HEAD_LENGTH: f32 = 6.;
+/// The thickness of lines which don't specify one.
+pub const DEFAULT_LINE_THICKNESS: f32 = 2.;
+
+/// Something shapes can be drawn onto, in world pixel coordinates with the origin at the
+/// top left of the level. The target maps world pixels onto its output: the gfx
+/// renderer's `graphics::FrameUpdater` through the renderer's camera and viewport, and
+/// `software_renderer::SoftwareRenderer` and `svg::Svg` at one output pixel per world
+/// pixel. Callers don't apply the camera themselves.
+///
+/// Colours are non-premultiplied RGBA. Later shapes are drawn over earlier ones.
+pub trait DrawTarget {
+    fn axis_aligned_rect(
+        &mut self,
+        top_left: Vector2<f32>,
+        size: Vector2<f32>,
+        colour: [f32; 4],
+    );
+    fn line_segment(
+        &mut self,
+        start: Vector2<f32>,
+        end: Vector2<f32>,
+        thickness: f32,
+        colour: [f32; 4],
+    );
+    /// Vertices may be in either winding order. Fewer than 3 vertices draws nothing.
+    fn convex_polygon(&mut self, vertices: &[Vector2<f32>], colour: [f32; 4]);
+    fn circle(&mut self, centre: Vector2<f32>, radius: f32, colour: [f32; 4]);
+
+    /// Lines along the edges of the rectangle, as drawn by the debug overlay.
+    fn rect_outline(
+        &mut self,
+        top_left: Vector2<f32>,
+        size: Vector2<f32>,
+        thickness: f32,
+        colour: [f32; 4],
+    ) {
+        let top_right = top_left + vec2(size.x, 0.);
+        let bottom_left = top_left + vec2(0., size.y);
+        let bottom_right = top_left + size;
+        self.line_segment(top_left, top_right, thickness, colour);
+        self.line_segment(top_right, bottom_right, thickness, colour);
+        self.line_segment(bottom_right, bottom_left, thickness, colour);
+        self.line_segment(bottom_left, top_left, thickness, colour);
+    }
+    /// A border `thickness` wide just inside the rectangle, made of rectangles which
+    /// don't overlap so translucent outlines have even corners.
+    fn filled_rect_outline(
+        &mut self,
+        top_left: Vector2<f32>,
+        size: Vector2<f32>,
+        thickness: f32,
+        colour: [f32; 4],
+    ) {
+        let thickness = thickness.min(size.x / 2.).min(size.y / 2.);
+        let side_height = size.y - thickness * 2.;
+        self.axis_aligned_rect(top_left, vec2(size.x, thickness), colour);
+        self.axis_aligned_rect(
+            top_left + vec2(0., size.y - thickness),
+            vec2(size.x, thickness),
+            colour,
+        );
+        self.axis_aligned_rect(
+            top_left + vec2(0., thickness),
+            vec2(thickness, side_height),
+            colour,
+        );
+        self.axis_aligned_rect(
+            top_left + vec2(size.x - thickness, thickness),
+            vec2(thickness, side_height),
+            colour,
+        );
+    }
+    fn arrow(
+        &mut self,
+        start: Vector2<f32>,
+        end: Vector2<f32>,
+        thickness: f32,
+        colour: [f32; 4],
+    ) {
+        let start_to_end = end - start;
+        if start_to_end.magnitude2() == 0. {
+            return;
+        }
+        let back = -start_to_end.normalize() * ARROW_HEAD_LENGTH;
+        let side = vec2(-back.y, back.x) / 2.;
+        self.line_segment(start, end, thickness, colour);
+        self.line_segment(end, end + back + side, thickness, colour);
+        self.line_segment(end, end + back - side, thickness, colour);
+    }
+}
@@ -0,0 +1,88 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Notices when a file is modified by polling its modification time, at most once per
+/// `interval`.
+#[derive(Debug, Clone)]
+pub struct FileWatcher {
+    path: PathBuf,
+    interval: Duration,
+    last_poll: Option<Instant>,
+    modified: Option<SystemTime>,
+}
+
+fn modified(path: &PathBuf) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+impl FileWatcher {
+    pub fn new<P: Into<PathBuf>>(path: P, interval: Duration) -> Self {
+        let path = path.into();
+        Self {
+            modified: modified(&path),
+            path,
+            interval,
+            last_poll: None,
+        }
+    }
+    /// Returns true if the file has changed since it was last checked. A file which is
+    /// missing (e.g. while an editor replaces it) doesn't count as changed until it
+    /// reappears.
+    pub fn poll(&mut self, now: Instant) -> bool {
+        if let Some(last_poll) = self.last_poll {
+            if now - last_poll < self.interval {
+                return false;
+            }
+        }
+        self.last_poll = Some(now);
+        match modified(&self.path) {
+            Some(modified) if Some(modified) != self.modified => {
+                self.modified = Some(modified);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::process;
+    use std::thread;
+
+    /// Writes a file until its modification time differs from `previous`, which takes up
+    /// to a second on file systems with coarse timestamps.
+    fn rewrite(path: &PathBuf, previous: Option<SystemTime>) {
+        fs::write(path, "rewritten").unwrap();
+        while modified(path) == previous {
+            thread::sleep(Duration::from_millis(10));
+            fs::write(path, "rewritten").unwrap();
+        }
+    }
+
+    #[test]
+    fn reports_changes_once_per_interval() {
+        let path = env::temp_dir().join(format!("file_watcher_test_{}", process::id()));
+        fs::write(&path, "initial").unwrap();
+        let interval = Duration::from_millis(250);
+        let mut watcher = FileWatcher::new(path.clone(), interval);
+        let start = Instant::now();
+        assert!(!watcher.poll(start));
+        assert!(!watcher.poll(start + interval));
+        rewrite(&path, modified(&path));
+        assert!(!watcher.poll(start + interval + interval / 2));
+        assert!(watcher.poll(start + interval * 2));
+        assert!(!watcher.poll(start + interval * 3));
+        let last_modified = modified(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(!watcher.poll(start + interval * 4));
+        rewrite(&path, last_modified);
+        assert!(watcher.poll(start + interval * 5));
+        fs::remove_file(&path).unwrap();
+    }
+}
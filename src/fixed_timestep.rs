@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+fn duration_nanos(duration: Duration) -> u64 {
+    duration.as_secs() * 1_000_000_000 + duration.subsec_nanos() as u64
+}
+
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    tick_nanos: u64,
+    max_ticks_per_frame: u32,
+    accumulator_nanos: u64,
+}
+
+impl FixedTimestep {
+    pub fn new(ticks_per_second: u32, max_ticks_per_frame: u32) -> Self {
+        assert!(ticks_per_second > 0, "tick rate must be positive");
+        Self {
+            tick_nanos: 1_000_000_000 / ticks_per_second as u64,
+            max_ticks_per_frame,
+            accumulator_nanos: 0,
+        }
+    }
+    pub fn tick_duration(&self) -> Duration {
+        Duration::from_nanos(self.tick_nanos)
+    }
+    /// Returns the number of ticks to simulate to account for `elapsed` time since the
+    /// previous frame. If more than `max_ticks_per_frame` ticks are due, the backlog is
+    /// dropped rather than letting the simulation fall further behind.
+    pub fn ticks_for_frame(&mut self, elapsed: Duration) -> u32 {
+        self.accumulator_nanos += duration_nanos(elapsed);
+        let ticks = self.accumulator_nanos / self.tick_nanos;
+        self.accumulator_nanos %= self.tick_nanos;
+        if ticks > self.max_ticks_per_frame as u64 {
+            self.max_ticks_per_frame
+        } else {
+            ticks as u32
+        }
+    }
+    /// How many ticks `elapsed` is, including the fraction of a tick, limited like
+    /// `ticks_for_frame`. For things which update once per frame rather than once per tick.
+    pub fn ticks_in(&self, elapsed: Duration) -> f32 {
+        let ticks = duration_nanos(elapsed) as f32 / self.tick_nanos as f32;
+        ticks.min(self.max_ticks_per_frame as f32)
+    }
+    /// How far between the previous and current tick the next frame should be drawn,
+    /// from 0 (previous tick) to 1 (current tick).
+    pub fn interpolation(&self) -> f32 {
+        self.accumulator_nanos as f32 / self.tick_nanos as f32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn accumulates() {
+        let mut timestep = FixedTimestep::new(100, 4);
+        assert_eq!(timestep.ticks_for_frame(ms(5)), 0);
+        assert_eq!(timestep.interpolation(), 0.5);
+        assert_eq!(timestep.ticks_for_frame(ms(7)), 1);
+        assert_eq!(timestep.ticks_for_frame(ms(27)), 2);
+        assert_eq!(timestep.interpolation(), 0.9);
+    }
+
+    #[test]
+    fn limits_catch_up() {
+        let mut timestep = FixedTimestep::new(100, 4);
+        assert_eq!(timestep.ticks_for_frame(ms(1000)), 4);
+        assert_eq!(timestep.ticks_for_frame(ms(10)), 1);
+        assert_eq!(timestep.ticks_in(ms(15)), 1.5);
+        assert_eq!(timestep.ticks_in(ms(1000)), 4.);
+    }
+}
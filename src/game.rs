@@ -1,14 +1,20 @@
-use fnv::FnvHashMap;
-use cgmath::{InnerSpace, Vector2, vec2};
-use pixel_num::sub_pixel_i64::{self, SubPixelI64};
+use fnv::{FnvHashMap, FnvHashSet, FnvHasher};
+use std::cmp::Reverse;
+use std::hash::Hasher;
+use vector::{Vector2, vec2};
+use pixel_num::sub_pixel_i64::{self, SubPixelI64, vector_to_f32_pixel};
 use shape::Shape;
-use axis_aligned_rect::AxisAlignedRect;
+use kinematic::KinematicPath;
+use contact::{CollisionEvent, ContactTracker, SensorEvent, SensorTracker};
+use aabb::Aabb;
+use draw::{DEFAULT_LINE_THICKNESS, DrawTarget};
+use level::{self, CameraBounds, EntityDesc, Flag, Level, LevelError, PathDesc, ShapeDesc};
 use loose_quad_tree::LooseQuadTree;
 use line_segment::LineSegment;
 use best::BestMap;
 use num::Zero;
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct InputModel {
     left: SubPixelI64,
     right: SubPixelI64,
@@ -33,6 +39,22 @@ impl InputModel {
     pub fn set_down(&mut self, value: f32) {
         self.down = clamp_float(value);
     }
+    pub fn to_sub_pixels(&self) -> [i64; 4] {
+        [
+            self.left.get(),
+            self.right.get(),
+            self.up.get(),
+            self.down.get(),
+        ]
+    }
+    pub fn from_sub_pixels(sub_pixels: [i64; 4]) -> Self {
+        Self {
+            left: SubPixelI64::new(sub_pixels[0]).clamp_zero_one_pixel(),
+            right: SubPixelI64::new(sub_pixels[1]).clamp_zero_one_pixel(),
+            up: SubPixelI64::new(sub_pixels[2]).clamp_zero_one_pixel(),
+            down: SubPixelI64::new(sub_pixels[3]).clamp_zero_one_pixel(),
+        }
+    }
     fn horizontal(&self) -> SubPixelI64 {
         self.right - self.left
     }
@@ -57,7 +79,16 @@ fn update_player_velocity(
 
 pub type EntityId = u32;
 
-#[derive(Default)]
+/// Entities this close to each other at the end of a tick are in contact.
+const CONTACT_DISTANCE: f32 = 1.;
+
+/// The most collisions an entity can slide off or push during one movement.
+const MAX_MOVEMENT_ITERATIONS: usize = 16;
+
+/// The longest chain of entities which a pushed entity can push in turn.
+const MAX_PUSH_DEPTH: usize = 8;
+
+#[derive(Default, Debug, Clone, Copy)]
 struct EntityIdAllocator {
     next: u32,
 }
@@ -73,7 +104,7 @@ impl EntityIdAllocator {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct SpatialInfo {
     entity_id: EntityId,
 }
@@ -81,19 +112,164 @@ struct SpatialInfo {
 type SpatialLooseQuadTree = LooseQuadTree<SpatialInfo, SubPixelI64>;
 
 pub struct RenderUpdate<'a> {
+    pub id: EntityId,
     pub position: Vector2<SubPixelI64>,
     pub shape: &'a Shape<SubPixelI64>,
-    pub colour: [f32; 3],
+    pub colour: [f32; 4],
+    pub layer: i32,
+    pub line_thickness: f32,
+    /// If set, rectangles and tile maps are drawn as outlines this thick.
+    pub outline: Option<f32>,
+}
+
+impl<'a> RenderUpdate<'a> {
+    pub fn draw<D: DrawTarget + ?Sized>(&self, target: &mut D) {
+        match self.shape {
+            &Shape::AxisAlignedRect(ref rect) => self.rect(
+                vector_to_f32_pixel(self.position),
+                vector_to_f32_pixel(rect.dimensions()),
+                target,
+            ),
+            &Shape::LineSegment(ref line_segment) => target.line_segment(
+                vector_to_f32_pixel(line_segment.start + self.position),
+                vector_to_f32_pixel(line_segment.end + self.position),
+                self.line_thickness,
+                self.colour,
+            ),
+            &Shape::TileMap(ref tile_map) => tile_map.for_each_solid_run(|top_left, size| {
+                self.rect(
+                    vector_to_f32_pixel(top_left + self.position),
+                    vector_to_f32_pixel(size),
+                    target,
+                )
+            }),
+        }
+    }
+    fn rect<D: DrawTarget + ?Sized>(
+        &self,
+        top_left: Vector2<f32>,
+        size: Vector2<f32>,
+        target: &mut D,
+    ) {
+        match self.outline {
+            Some(thickness) => {
+                target.filled_rect_outline(top_left, size, thickness, self.colour)
+            }
+            None => target.axis_aligned_rect(top_left, size, self.colour),
+        }
+    }
+}
+
+/// One call to `movement_step`, recorded while movement debugging is enabled.
+#[derive(Debug, Clone)]
+pub struct MovementDebug {
+    pub id: EntityId,
+    /// The entity's aabb before the step.
+    pub start: Aabb<SubPixelI64>,
+    /// The union of the start and end aabbs, which is searched for collisions.
+    pub swept: Aabb<SubPixelI64>,
+    pub collision: Option<CollisionDebug>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CollisionDebug {
+    pub entity_id: EntityId,
+    pub line_segment: LineSegment<SubPixelI64>,
+    pub allowed_movement: Vector2<SubPixelI64>,
 }
 
 pub struct GameState {
     player_id: Option<EntityId>,
     entity_id_allocator: EntityIdAllocator,
     position: FnvHashMap<EntityId, Vector2<SubPixelI64>>,
+    previous_position: FnvHashMap<EntityId, Vector2<SubPixelI64>>,
     shape: FnvHashMap<EntityId, Shape<SubPixelI64>>,
-    colour: FnvHashMap<EntityId, [f32; 3]>,
+    colour: FnvHashMap<EntityId, [f32; 4]>,
+    layer: FnvHashMap<EntityId, i32>,
+    line_thickness: FnvHashMap<EntityId, f32>,
+    outline: FnvHashMap<EntityId, f32>,
     velocity: FnvHashMap<EntityId, Vector2<SubPixelI64>>,
+    kinematic: FnvHashMap<EntityId, KinematicPath>,
+    pushable: FnvHashSet<EntityId>,
+    mass: FnvHashMap<EntityId, i64>,
+    sensor: FnvHashSet<EntityId>,
+    priority: FnvHashMap<EntityId, i32>,
     quad_tree: SpatialLooseQuadTree,
+    crushed: Vec<EntityId>,
+    contact_tracker: ContactTracker,
+    collision_events: Vec<CollisionEvent>,
+    swept: FnvHashMap<EntityId, Aabb<SubPixelI64>>,
+    sensor_tracker: SensorTracker,
+    sensor_events: Vec<SensorEvent>,
+    movement_debug: Option<Vec<MovementDebug>>,
+    camera_bounds: Option<Aabb<SubPixelI64>>,
+}
+
+impl Clone for GameState {
+    fn clone(&self) -> Self {
+        Self {
+            player_id: self.player_id,
+            entity_id_allocator: self.entity_id_allocator,
+            position: self.position.clone(),
+            previous_position: self.previous_position.clone(),
+            shape: self.shape.clone(),
+            colour: self.colour.clone(),
+            layer: self.layer.clone(),
+            line_thickness: self.line_thickness.clone(),
+            outline: self.outline.clone(),
+            velocity: self.velocity.clone(),
+            kinematic: self.kinematic.clone(),
+            pushable: self.pushable.clone(),
+            mass: self.mass.clone(),
+            sensor: self.sensor.clone(),
+            priority: self.priority.clone(),
+            quad_tree: self.quad_tree.clone(),
+            crushed: self.crushed.clone(),
+            contact_tracker: self.contact_tracker.clone(),
+            collision_events: self.collision_events.clone(),
+            swept: self.swept.clone(),
+            sensor_tracker: self.sensor_tracker.clone(),
+            sensor_events: self.sensor_events.clone(),
+            movement_debug: self.movement_debug.clone(),
+            camera_bounds: self.camera_bounds,
+        }
+    }
+    fn clone_from(&mut self, source: &Self) {
+        self.player_id = source.player_id;
+        self.camera_bounds = source.camera_bounds;
+        self.entity_id_allocator = source.entity_id_allocator;
+        self.position.clone_from(&source.position);
+        self.previous_position.clone_from(&source.previous_position);
+        self.shape.clone_from(&source.shape);
+        self.colour.clone_from(&source.colour);
+        self.layer.clone_from(&source.layer);
+        self.line_thickness.clone_from(&source.line_thickness);
+        self.outline.clone_from(&source.outline);
+        self.velocity.clone_from(&source.velocity);
+        self.kinematic.clone_from(&source.kinematic);
+        self.pushable.clone_from(&source.pushable);
+        self.mass.clone_from(&source.mass);
+        self.sensor.clone_from(&source.sensor);
+        self.priority.clone_from(&source.priority);
+        self.quad_tree.clone_from(&source.quad_tree);
+        self.crushed.clone_from(&source.crushed);
+        self.contact_tracker.clone_from(&source.contact_tracker);
+        self.collision_events.clone_from(&source.collision_events);
+        self.swept.clone_from(&source.swept);
+        self.sensor_tracker.clone_from(&source.sensor_tracker);
+        self.sensor_events.clone_from(&source.sensor_events);
+        // Debugging stays as it was, so restoring a snapshot doesn't turn it on or off.
+        if let Some(ref mut movement_debug) = self.movement_debug {
+            movement_debug.clear();
+        }
+    }
+}
+
+/// A copy of a `GameState` which can be restored later, for example to roll back to the
+/// last confirmed tick when a late input arrives.
+#[derive(Clone)]
+pub struct Snapshot {
+    state: GameState,
 }
 
 enum MovementStep {
@@ -105,6 +281,7 @@ enum MovementStep {
         allowed_movement: Vector2<SubPixelI64>,
         destination: Vector2<SubPixelI64>,
         line_segment: LineSegment<SubPixelI64>,
+        entity_id: EntityId,
     },
 }
 
@@ -113,8 +290,10 @@ fn movement_step(
     position: Vector2<SubPixelI64>,
     position_table: &FnvHashMap<EntityId, Vector2<SubPixelI64>>,
     shape_table: &FnvHashMap<EntityId, Shape<SubPixelI64>>,
+    sensor_table: &FnvHashSet<EntityId>,
     quad_tree: &SpatialLooseQuadTree,
     movement: Vector2<SubPixelI64>,
+    ignore_id: Option<EntityId>,
 ) -> MovementStep {
     if movement.x.is_zero() && movement.y.is_zero() {
         return MovementStep::NoMovement;
@@ -130,7 +309,9 @@ fn movement_step(
              SpatialInfo {
                  entity_id: other_id,
              }| {
-                if *other_id != id {
+                if *other_id != id && Some(*other_id) != ignore_id
+                    && !sensor_table.contains(other_id)
+                {
                     if let Some(stationary_position) = position_table.get(other_id) {
                         if let Some(stationary_shape) = shape_table.get(other_id) {
                             if let Some(collision_info) = shape.movement_collision_test(
@@ -139,11 +320,14 @@ fn movement_step(
                                 *stationary_position,
                                 movement,
                             ) {
+                                // Ties are broken by entity id so the result doesn't depend
+                                // on the order of entities in the quad tree.
                                 closest_collision.insert_le(
-                                    collision_info.magnitude2,
+                                    (collision_info.magnitude2, *other_id),
                                     (
                                         collision_info.allowed_movement,
                                         collision_info.line_segment,
+                                        *other_id,
                                     ),
                                 );
                             }
@@ -156,68 +340,62 @@ fn movement_step(
             None => MovementStep::NoCollision {
                 destination: position + movement,
             },
-            Some((allowed_movement, line_segment)) => MovementStep::Collision {
+            Some((allowed_movement, line_segment, entity_id)) => MovementStep::Collision {
                 allowed_movement,
                 destination: position + allowed_movement,
                 line_segment,
+                entity_id,
             },
         };
     }
     MovementStep::NoMovement
 }
 
-fn position_after_movement(
-    id: EntityId,
-    position_table: &FnvHashMap<EntityId, Vector2<SubPixelI64>>,
-    shape_table: &FnvHashMap<EntityId, Shape<SubPixelI64>>,
-    quad_tree: &SpatialLooseQuadTree,
-    mut movement: Vector2<SubPixelI64>,
-) -> Option<Vector2<SubPixelI64>> {
-    let mut position = if let Some(position) = position_table.get(&id) {
-        *position
-    } else {
-        return None;
-    };
-    const MAX_ITERATIONS: usize = 16;
-    for _ in 0..MAX_ITERATIONS {
-        match movement_step(
-            id,
-            position,
-            position_table,
-            shape_table,
-            quad_tree,
-            movement,
-        ) {
-            MovementStep::NoMovement => return Some(position),
-            MovementStep::NoCollision { destination } => return Some(destination),
-            MovementStep::Collision {
-                allowed_movement,
-                destination,
-                line_segment,
-            } => {
-                position = destination;
-                let remaining_movement =
-                    sub_pixel_i64::vector_to_f32_sub_pixel(movement - allowed_movement);
-                let collision_surface_direction =
-                    sub_pixel_i64::vector_to_f32_sub_pixel(line_segment.vector())
-                        .normalize();
-                let slide_movement_float =
-                    remaining_movement.project_on(collision_surface_direction);
-                let remaining_to_slide_direction =
-                    (slide_movement_float - remaining_movement).normalize();
-                let padding = remaining_to_slide_direction * 0.1
-                    * sub_pixel_i64::SUB_PIXELS_PER_PIXEL as f32;
-                let slide_movement = sub_pixel_i64::vector_from_f32_sub_pixel(
-                    slide_movement_float + padding,
-                );
-                if sub_pixel_i64::vector_is_zero(slide_movement) {
-                    break;
+fn slide_movement(
+    movement: Vector2<SubPixelI64>,
+    allowed_movement: Vector2<SubPixelI64>,
+    line_segment: LineSegment<SubPixelI64>,
+) -> Vector2<SubPixelI64> {
+    let remaining_movement =
+        sub_pixel_i64::vector_to_f32_sub_pixel(movement - allowed_movement);
+    let collision_surface_direction =
+        sub_pixel_i64::vector_to_f32_sub_pixel(line_segment.vector()).normalize();
+    let slide_movement_float = remaining_movement.project_on(collision_surface_direction);
+    let remaining_to_slide_direction =
+        (slide_movement_float - remaining_movement).normalize();
+    let padding =
+        remaining_to_slide_direction * 0.1 * sub_pixel_i64::SUB_PIXELS_PER_PIXEL as f32;
+    sub_pixel_i64::vector_from_f32_sub_pixel(slide_movement_float + padding)
+}
+
+fn hash_vector<H: Hasher>(v: Vector2<SubPixelI64>, hasher: &mut H) {
+    hasher.write_i64(v.x.get());
+    hasher.write_i64(v.y.get());
+}
+
+fn hash_shape<H: Hasher>(shape: &Shape<SubPixelI64>, hasher: &mut H) {
+    match shape {
+        &Shape::AxisAlignedRect(ref rect) => {
+            hasher.write_u8(0);
+            hash_vector(rect.dimensions(), hasher);
+        }
+        &Shape::LineSegment(ref line_segment) => {
+            hasher.write_u8(1);
+            hash_vector(line_segment.start, hasher);
+            hash_vector(line_segment.end, hasher);
+        }
+        &Shape::TileMap(ref tile_map) => {
+            hasher.write_u8(2);
+            hash_vector(tile_map.tile_size(), hasher);
+            hasher.write_u64(tile_map.width() as u64);
+            hasher.write_u64(tile_map.height() as u64);
+            for y in 0..tile_map.height() {
+                for x in 0..tile_map.width() {
+                    hasher.write_u8(tile_map.is_solid(x as i64, y as i64) as u8);
                 }
-                movement = slide_movement
             }
         }
     }
-    Some(position)
 }
 
 impl GameState {
@@ -226,34 +404,67 @@ impl GameState {
             player_id: None,
             entity_id_allocator: Default::default(),
             position: Default::default(),
+            previous_position: Default::default(),
             shape: Default::default(),
             colour: Default::default(),
+            layer: Default::default(),
+            line_thickness: Default::default(),
+            outline: Default::default(),
             velocity: Default::default(),
+            kinematic: Default::default(),
+            pushable: Default::default(),
+            mass: Default::default(),
+            sensor: Default::default(),
+            priority: Default::default(),
             quad_tree: LooseQuadTree::new(vec2(
                 SubPixelI64::new_pixels_f32(size_hint.x),
                 SubPixelI64::new_pixels_f32(size_hint.y),
             )),
+            crushed: Vec::new(),
+            contact_tracker: Default::default(),
+            collision_events: Vec::new(),
+            swept: Default::default(),
+            sensor_tracker: Default::default(),
+            sensor_events: Vec::new(),
+            movement_debug: None,
+            camera_bounds: None,
         }
     }
     fn clear(&mut self) {
         self.player_id = None;
+        self.camera_bounds = None;
         self.entity_id_allocator.reset();
         self.position.clear();
+        self.previous_position.clear();
         self.shape.clear();
         self.colour.clear();
+        self.layer.clear();
+        self.line_thickness.clear();
+        self.outline.clear();
         self.velocity.clear();
+        self.kinematic.clear();
+        self.pushable.clear();
+        self.mass.clear();
+        self.sensor.clear();
+        self.priority.clear();
+        self.quad_tree.clear();
+        self.crushed.clear();
+        self.contact_tracker.clear();
+        self.collision_events.clear();
+        self.swept.clear();
+        self.sensor_tracker.clear();
+        self.sensor_events.clear();
+        if let Some(ref mut movement_debug) = self.movement_debug {
+            movement_debug.clear();
+        }
     }
     fn add_entity(
         &mut self,
-        position: Vector2<f32>,
+        position: Vector2<SubPixelI64>,
         shape: Shape<SubPixelI64>,
-        colour: [f32; 3],
+        colour: [f32; 4],
     ) -> EntityId {
         let id = self.entity_id_allocator.allocate();
-        let position = vec2(
-            SubPixelI64::new_pixels_f32(position.x),
-            SubPixelI64::new_pixels_f32(position.y),
-        );
         self.position.insert(id, position);
 
         self.quad_tree
@@ -262,74 +473,1408 @@ impl GameState {
         self.colour.insert(id, colour);
         id
     }
+    fn set_position(&mut self, id: EntityId, position: Vector2<SubPixelI64>) {
+        if let Some(shape) = self.shape.get(&id) {
+            let aabb = shape.aabb(position);
+            let mut swept = aabb;
+            if let Some(&old_position) = self.position.get(&id) {
+                let old_aabb = shape.aabb(old_position);
+                self.quad_tree.remove(&old_aabb, |info| info.entity_id == id);
+                swept = swept.union(&old_aabb);
+            }
+            self.quad_tree.insert(aabb, SpatialInfo { entity_id: id });
+            self.swept
+                .entry(id)
+                .and_modify(|existing| *existing = existing.union(&swept))
+                .or_insert(swept);
+        }
+        self.position.insert(id, position);
+    }
+    fn position_after_movement(
+        &mut self,
+        id: EntityId,
+        mut movement: Vector2<SubPixelI64>,
+        ignore_id: Option<EntityId>,
+    ) -> Option<Vector2<SubPixelI64>> {
+        let mut position = if let Some(position) = self.position.get(&id) {
+            *position
+        } else {
+            return None;
+        };
+        let mut pushed_ids = Vec::new();
+        for _ in 0..MAX_MOVEMENT_ITERATIONS {
+            let step = movement_step(
+                id,
+                position,
+                &self.position,
+                &self.shape,
+                &self.sensor,
+                &self.quad_tree,
+                movement,
+                ignore_id,
+            );
+            self.record_movement_debug(id, position, movement, &step);
+            match step {
+                MovementStep::NoMovement => return Some(position),
+                MovementStep::NoCollision { destination } => return Some(destination),
+                MovementStep::Collision {
+                    allowed_movement,
+                    destination,
+                    line_segment,
+                    entity_id,
+                } => {
+                    position = destination;
+                    self.record_contact(id, entity_id, line_segment, destination);
+                    if self.pushable.contains(&entity_id) && !pushed_ids.contains(&entity_id) {
+                        pushed_ids.push(entity_id);
+                        let push_movement =
+                            self.push_movement(id, entity_id, movement - allowed_movement);
+                        let pushed = self.push(entity_id, push_movement, ignore_id, 0);
+                        if !sub_pixel_i64::vector_is_zero(pushed) {
+                            movement = pushed;
+                            continue;
+                        }
+                    }
+                    let slide_movement =
+                        slide_movement(movement, allowed_movement, line_segment);
+                    if sub_pixel_i64::vector_is_zero(slide_movement) {
+                        break;
+                    }
+                    movement = slide_movement
+                }
+            }
+        }
+        Some(position)
+    }
+    fn record_movement_debug(
+        &mut self,
+        id: EntityId,
+        position: Vector2<SubPixelI64>,
+        movement: Vector2<SubPixelI64>,
+        step: &MovementStep,
+    ) {
+        if let (Some(movement_debug), Some(shape)) =
+            (self.movement_debug.as_mut(), self.shape.get(&id))
+        {
+            let collision = match step {
+                &MovementStep::NoMovement => return,
+                &MovementStep::NoCollision { .. } => None,
+                &MovementStep::Collision {
+                    allowed_movement,
+                    line_segment,
+                    entity_id,
+                    ..
+                } => Some(CollisionDebug {
+                    entity_id,
+                    line_segment,
+                    allowed_movement,
+                }),
+            };
+            let start = shape.aabb(position);
+            movement_debug.push(MovementDebug {
+                id,
+                start,
+                swept: start.union(&shape.aabb(position + movement)),
+                collision,
+            });
+        }
+    }
+    fn record_contact(
+        &mut self,
+        id: EntityId,
+        other_id: EntityId,
+        edge: LineSegment<SubPixelI64>,
+        position: Vector2<SubPixelI64>,
+    ) {
+        if let Some(shape) = self.shape.get(&id) {
+            let contact_point = edge.closest_point(shape.aabb(position).centre());
+            self.contact_tracker.record(id, other_id, edge, contact_point);
+        }
+    }
+    /// Records contacts with everything within `CONTACT_DISTANCE` of each dynamic entity,
+    /// so entities which are resting or being carried stay in contact.
+    fn record_adjacent_contacts(&mut self) {
+        let distance = SubPixelI64::new_pixels_f32(CONTACT_DISTANCE);
+        let probes = [
+            vec2(distance, Zero::zero()),
+            vec2(-distance, Zero::zero()),
+            vec2(Zero::zero(), distance),
+            vec2(Zero::zero(), -distance),
+        ];
+        let dynamic_ids =
+            self.update_order(self.velocity.keys().chain(self.pushable.iter()).cloned());
+        for id in dynamic_ids {
+            if self.sensor.contains(&id) || self.kinematic.contains_key(&id) {
+                continue;
+            }
+            let (position, shape) = match (self.position.get(&id), self.shape.get(&id)) {
+                (Some(&position), Some(shape)) => (position, shape),
+                _ => continue,
+            };
+            let aabb = shape.aabb(position);
+            let search_aabb = Aabb::new(
+                aabb.top_left() - vec2(distance, distance),
+                aabb.size() + vec2(distance, distance) * SubPixelI64::new(2),
+            );
+            let mut adjacent = Vec::new();
+            {
+                let position_table = &self.position;
+                let shape_table = &self.shape;
+                let sensor_table = &self.sensor;
+                let contact_tracker = &self.contact_tracker;
+                self.quad_tree.for_each_intersection(
+                    &search_aabb,
+                    |_other_aabb,
+                     SpatialInfo {
+                         entity_id: other_id,
+                     }| {
+                        if *other_id == id || sensor_table.contains(other_id)
+                            || contact_tracker.is_touching(id, *other_id)
+                        {
+                            return;
+                        }
+                        if let (Some(&other_position), Some(other_shape)) =
+                            (position_table.get(other_id), shape_table.get(other_id))
+                        {
+                            let closest = probes
+                                .iter()
+                                .filter_map(|&probe| {
+                                    shape.movement_collision_test(
+                                        position,
+                                        other_shape,
+                                        other_position,
+                                        probe,
+                                    )
+                                })
+                                .min_by_key(|collision_info| collision_info.magnitude2);
+                            if let Some(collision_info) = closest {
+                                adjacent.push((*other_id, collision_info.line_segment));
+                            }
+                        }
+                    },
+                );
+            }
+            adjacent.sort_by_key(|&(other_id, _)| other_id);
+            for (other_id, edge) in adjacent {
+                self.record_contact(id, other_id, edge, position);
+            }
+        }
+    }
+    fn push_movement(
+        &self,
+        pusher_id: EntityId,
+        pushed_id: EntityId,
+        movement: Vector2<SubPixelI64>,
+    ) -> Vector2<SubPixelI64> {
+        if let (Some(&pusher_mass), Some(&pushed_mass)) =
+            (self.mass.get(&pusher_id), self.mass.get(&pushed_id))
+        {
+            if pushed_mass > pusher_mass {
+                return movement * SubPixelI64::new(pusher_mass)
+                    / SubPixelI64::new(pushed_mass);
+            }
+        }
+        movement
+    }
+    fn push(
+        &mut self,
+        id: EntityId,
+        movement: Vector2<SubPixelI64>,
+        ignore_id: Option<EntityId>,
+        depth: usize,
+    ) -> Vector2<SubPixelI64> {
+        let start = if let Some(position) = self.position.get(&id) {
+            *position
+        } else {
+            return vec2(Zero::zero(), Zero::zero());
+        };
+        let mut position = start;
+        let mut movement = movement;
+        let mut pushed = false;
+        for _ in 0..MAX_MOVEMENT_ITERATIONS {
+            let step = movement_step(
+                id,
+                position,
+                &self.position,
+                &self.shape,
+                &self.sensor,
+                &self.quad_tree,
+                movement,
+                ignore_id,
+            );
+            self.record_movement_debug(id, position, movement, &step);
+            match step {
+                MovementStep::NoMovement => break,
+                MovementStep::NoCollision { destination } => {
+                    position = destination;
+                    break;
+                }
+                MovementStep::Collision {
+                    allowed_movement,
+                    destination,
+                    line_segment,
+                    entity_id,
+                } => {
+                    position = destination;
+                    self.record_contact(id, entity_id, line_segment, destination);
+                    if pushed || depth >= MAX_PUSH_DEPTH
+                        || !self.pushable.contains(&entity_id)
+                    {
+                        break;
+                    }
+                    pushed = true;
+                    let push_movement =
+                        self.push_movement(id, entity_id, movement - allowed_movement);
+                    movement = self.push(entity_id, push_movement, ignore_id, depth + 1);
+                    if sub_pixel_i64::vector_is_zero(movement) {
+                        break;
+                    }
+                }
+            }
+        }
+        self.set_position(id, position);
+        position - start
+    }
+    fn is_riding(&self, id: EntityId, platform_id: EntityId) -> bool {
+        if let (Some(&position), Some(shape)) = (self.position.get(&id), self.shape.get(&id))
+        {
+            if let (Some(&platform_position), Some(platform_shape)) = (
+                self.position.get(&platform_id),
+                self.shape.get(&platform_id),
+            ) {
+                let down = vec2(Zero::zero(), SubPixelI64::new_pixels_f32(1.));
+                return shape
+                    .movement_collision_test(position, platform_shape, platform_position, down)
+                    .is_some();
+            }
+        }
+        false
+    }
+    fn platform_push(
+        &self,
+        id: EntityId,
+        platform_id: EntityId,
+        movement: Vector2<SubPixelI64>,
+    ) -> Option<Vector2<SubPixelI64>> {
+        if let (Some(&position), Some(shape)) = (self.position.get(&id), self.shape.get(&id))
+        {
+            if let (Some(&platform_position), Some(platform_shape)) = (
+                self.position.get(&platform_id),
+                self.shape.get(&platform_id),
+            ) {
+                // Moving the pushed entity towards the platform is equivalent to moving the
+                // platform towards the pushed entity.
+                return shape
+                    .movement_collision_test(
+                        position,
+                        platform_shape,
+                        platform_position,
+                        -movement,
+                    )
+                    .map(|collision_info| movement + collision_info.allowed_movement);
+            }
+        }
+        None
+    }
+    /// Whether an entity still overlaps a platform which has moved into it. Carried and
+    /// pushed entities are all rectangles, like platforms, so their aabbs are exact.
+    fn is_in_way(&self, id: EntityId, platform_id: EntityId) -> bool {
+        match (self.aabb(id), self.aabb(platform_id)) {
+            (Some(aabb), Some(platform_aabb)) => aabb.is_overlapping(&platform_aabb),
+            _ => false,
+        }
+    }
+    /// Entities which are blocked while being carried just stop. They're only crushed if
+    /// they couldn't get out of the platform's way.
+    fn carry(&mut self, id: EntityId, platform_id: EntityId, movement: Vector2<SubPixelI64>) {
+        self.push(id, movement, Some(platform_id), 0);
+        if self.is_in_way(id, platform_id) {
+            self.crushed.push(id);
+        }
+    }
+    pub fn set_priority(&mut self, id: EntityId, priority: i32) {
+        self.priority.insert(id, priority);
+    }
+    /// Entities are updated in order of decreasing priority (0 by default), and entities with
+    /// equal priority are updated in order of increasing id. This makes the outcome of
+    /// interactions between moving entities independent of hash map iteration order.
+    fn update_order<I: IntoIterator<Item = EntityId>>(&self, ids: I) -> Vec<EntityId> {
+        let mut ids = ids.into_iter().collect::<Vec<_>>();
+        ids.sort_by_key(|id| (Reverse(self.priority.get(id).cloned().unwrap_or(0)), *id));
+        ids.dedup();
+        ids
+    }
+    fn update_kinematic(&mut self) {
+        let platform_ids = self.update_order(self.kinematic.keys().cloned());
+        for platform_id in platform_ids {
+            let position = if let Some(position) = self.position.get(&platform_id) {
+                *position
+            } else {
+                continue;
+            };
+            let movement = if let Some(path) = self.kinematic.get_mut(&platform_id) {
+                path.movement(position)
+            } else {
+                continue;
+            };
+            if sub_pixel_i64::vector_is_zero(movement) {
+                continue;
+            }
+            if self.sensor.contains(&platform_id) {
+                self.set_position(platform_id, position + movement);
+                continue;
+            }
+            let dynamic_ids =
+                self.update_order(self.velocity.keys().chain(self.pushable.iter()).cloned());
+            let carried = dynamic_ids
+                .into_iter()
+                .filter(|id| !self.kinematic.contains_key(id) && !self.sensor.contains(id))
+                .filter_map(|id| {
+                    if self.is_riding(id, platform_id) {
+                        Some((id, movement))
+                    } else {
+                        self.platform_push(id, platform_id, movement)
+                            .map(|push| (id, push))
+                    }
+                })
+                .collect::<Vec<_>>();
+            self.set_position(platform_id, position + movement);
+            for (id, movement) in carried {
+                self.carry(id, platform_id, movement);
+            }
+        }
+    }
+    /// The position of every entity, in id order.
+    pub fn positions(&self) -> Vec<(EntityId, Vector2<SubPixelI64>)> {
+        let mut positions = self.position
+            .iter()
+            .map(|(&id, &position)| (id, position))
+            .collect::<Vec<_>>();
+        positions.sort_by_key(|&(id, _)| id);
+        positions
+    }
+    /// The velocity of every entity which has one, in id order.
+    pub fn velocities(&self) -> Vec<(EntityId, Vector2<SubPixelI64>)> {
+        let mut velocities = self.velocity
+            .iter()
+            .map(|(&id, &velocity)| (id, velocity))
+            .collect::<Vec<_>>();
+        velocities.sort_by_key(|&(id, _)| id);
+        velocities
+    }
+    pub fn aabb(&self, id: EntityId) -> Option<Aabb<SubPixelI64>> {
+        match (self.shape.get(&id), self.position.get(&id)) {
+            (Some(shape), Some(&position)) => Some(shape.aabb(position)),
+            _ => None,
+        }
+    }
+    pub fn size(&self) -> Vector2<SubPixelI64> {
+        self.quad_tree.size()
+    }
+    /// See `LooseQuadTree::for_each_node`.
+    pub fn for_each_quad_tree_node<F: FnMut(&Aabb<SubPixelI64>, usize)>(&self, f: F) {
+        self.quad_tree.for_each_node(f);
+    }
+    pub fn player_id(&self) -> Option<EntityId> {
+        self.player_id
+    }
+    /// The region the camera should stay inside, which is the whole level unless the level
+    /// says otherwise.
+    pub fn camera_bounds(&self) -> Aabb<SubPixelI64> {
+        self.camera_bounds.unwrap_or_else(|| {
+            Aabb::new(vec2(Zero::zero(), Zero::zero()), self.quad_tree.size())
+        })
+    }
+    pub fn player_position(&self) -> Option<Vector2<SubPixelI64>> {
+        self.player_id.and_then(|id| self.position.get(&id).cloned())
+    }
+    /// Moves the player without sweeping through the space in between, so nothing on the
+    /// way is touched and it isn't drawn moving there.
+    pub fn teleport_player(&mut self, position: Vector2<SubPixelI64>) {
+        if let Some(id) = self.player_id {
+            self.set_position(id, position);
+            self.previous_position.insert(id, position);
+            self.swept.remove(&id);
+        }
+    }
+    /// Movement debugging records every movement step of each update, for
+    /// `debug_overlay`. It's off by default.
+    pub fn set_movement_debug(&mut self, enabled: bool) {
+        if enabled != self.movement_debug.is_some() {
+            self.movement_debug = if enabled { Some(Vec::new()) } else { None };
+        }
+    }
+    /// The movement steps of the last update.
+    pub fn movement_debug(&self) -> &[MovementDebug] {
+        self.movement_debug.as_ref().map_or(&[], |steps| steps)
+    }
+    pub fn crushed(&self) -> &[EntityId] {
+        &self.crushed
+    }
+    pub fn collision_events(&self) -> &[CollisionEvent] {
+        &self.collision_events
+    }
+    pub fn sensor_events(&self) -> &[SensorEvent] {
+        &self.sensor_events
+    }
+    fn update_sensors(&mut self) {
+        let sensor_table = &self.sensor;
+        let position_table = &self.position;
+        let shape_table = &self.shape;
+        let quad_tree = &self.quad_tree;
+        let swept = &self.swept;
+        let sensor_tracker = &mut self.sensor_tracker;
+        let ids = self.velocity
+            .keys()
+            .chain(self.pushable.iter())
+            .chain(self.kinematic.keys());
+        for &id in ids {
+            if sensor_table.contains(&id) {
+                continue;
+            }
+            let aabb = if let Some(aabb) = swept.get(&id) {
+                *aabb
+            } else if let (Some(&position), Some(shape)) =
+                (position_table.get(&id), shape_table.get(&id))
+            {
+                shape.aabb(position)
+            } else {
+                continue;
+            };
+            quad_tree.for_each_intersection(
+                &aabb,
+                |_sensor_aabb,
+                 SpatialInfo {
+                     entity_id: sensor_id,
+                 }| {
+                    if *sensor_id != id && sensor_table.contains(sensor_id) {
+                        if let (Some(&sensor_position), Some(sensor_shape)) =
+                            (position_table.get(sensor_id), shape_table.get(sensor_id))
+                        {
+                            if sensor_shape.is_intersecting_aabb(sensor_position, &aabb) {
+                                sensor_tracker.record(*sensor_id, id);
+                            }
+                        }
+                    }
+                },
+            );
+        }
+        self.swept.clear();
+        self.sensor_tracker.end_tick(&mut self.sensor_events);
+    }
     pub fn init_demo(&mut self) {
+        include_str!("../levels/demo.json")
+            .parse::<Level>()
+            .and_then(|level| self.load_level(&level))
+            .expect("Invalid demo level");
+    }
+    /// Replaces every entity with the level's. Invalid levels are rejected, leaving the
+    /// state as it was.
+    pub fn load_level(&mut self, level: &Level) -> Result<(), LevelError> {
+        level.validate()?;
         self.clear();
-        let player_id = self.add_entity(
-            vec2(200., 50.),
-            Shape::AxisAlignedRect(AxisAlignedRect::new(vec2(
-                SubPixelI64::new_pixels_f32(32.),
-                SubPixelI64::new_pixels_f32(64.),
-            ))),
-            [1., 0., 0.],
-        );
-
-        self.player_id = Some(player_id);
-        self.velocity.insert(
-            player_id,
-            vec2(
-                SubPixelI64::new_pixels_f32(0.),
-                SubPixelI64::new_pixels_f32(0.),
-            ),
-        );
-
-        self.add_entity(
-            vec2(50., 200.),
-            Shape::AxisAlignedRect(AxisAlignedRect::new(vec2(
-                SubPixelI64::new_pixels_f32(400.),
-                SubPixelI64::new_pixels_f32(20.),
-            ))),
-            [1., 1., 0.],
-        );
-
-        self.add_entity(
-            vec2(20., 20.),
-            Shape::LineSegment(LineSegment::new(
-                vec2(Zero::zero(), Zero::zero()),
-                vec2(
-                    SubPixelI64::new_pixels_f32(50.),
-                    SubPixelI64::new_pixels_f32(100.),
-                ),
-            )),
-            [0., 1., 0.],
-        );
+        self.quad_tree = LooseQuadTree::new(level::vector_from_pixels(level.size));
+        self.camera_bounds = level.camera_bounds.map(|bounds| {
+            Aabb::new(
+                level::vector_from_pixels(bounds.position),
+                level::vector_from_pixels(bounds.size),
+            )
+        });
+        for entity in level.entities.iter() {
+            let id = self.add_entity(
+                level::vector_from_pixels(entity.position),
+                entity.shape.to_shape(),
+                entity.rgba(),
+            );
+            if let Some(layer) = entity.layer {
+                self.layer.insert(id, layer);
+            }
+            if let Some(line_thickness) = entity.line_thickness {
+                self.line_thickness.insert(id, line_thickness);
+            }
+            if let Some(outline) = entity.outline {
+                self.outline.insert(id, outline);
+            }
+            if let Some(velocity) = entity.velocity {
+                self.velocity.insert(id, level::vector_from_pixels(velocity));
+            }
+            if entity.has_flag(Flag::Player) {
+                self.player_id = Some(id);
+            }
+            if entity.has_flag(Flag::Pushable) {
+                self.pushable.insert(id);
+            }
+            if entity.has_flag(Flag::Sensor) {
+                self.sensor.insert(id);
+            }
+            if let Some(mass) = entity.mass {
+                self.mass.insert(id, mass);
+            }
+            if let Some(priority) = entity.priority {
+                self.priority.insert(id, priority);
+            }
+            if let Some(ref path) = entity.path {
+                let waypoints = path.waypoints
+                    .iter()
+                    .map(|&waypoint| level::vector_from_pixels(waypoint))
+                    .collect();
+                self.kinematic.insert(
+                    id,
+                    KinematicPath::new(waypoints, SubPixelI64::new_pixels_f64(path.speed)),
+                );
+            }
+        }
+        Ok(())
+    }
+    /// Loads a new version of the current level, e.g. after its file is edited. With
+    /// `keep_player_position` the player stays where it was instead of going back to its
+    /// start. Returns a snapshot of the level as it starts, for resetting to.
+    pub fn reload_level(
+        &mut self,
+        level: &Level,
+        keep_player_position: bool,
+    ) -> Result<Snapshot, LevelError> {
+        let player_position = self.player_position();
+        self.load_level(level)?;
+        let snapshot = self.snapshot();
+        if keep_player_position {
+            if let Some(player_position) = player_position {
+                self.teleport_player(player_position);
+            }
+        }
+        Ok(snapshot)
+    }
+    pub fn to_level(&self) -> Level {
+        let mut ids = self.position.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+        let entities = ids.into_iter()
+            .filter_map(|id| {
+                let shape = self.shape.get(&id)?;
+                let mut flags = Vec::new();
+                if self.player_id == Some(id) {
+                    flags.push(Flag::Player);
+                }
+                if self.pushable.contains(&id) {
+                    flags.push(Flag::Pushable);
+                }
+                if self.sensor.contains(&id) {
+                    flags.push(Flag::Sensor);
+                }
+                let colour = self.colour.get(&id).cloned().unwrap_or([1., 1., 1., 1.]);
+                Some(EntityDesc {
+                    position: level::vector_to_pixels(self.position[&id]),
+                    shape: ShapeDesc::from_shape(shape),
+                    colour: [colour[0], colour[1], colour[2]],
+                    alpha: if colour[3] < 1. { Some(colour[3]) } else { None },
+                    layer: self.layer.get(&id).cloned(),
+                    line_thickness: self.line_thickness.get(&id).cloned(),
+                    outline: self.outline.get(&id).cloned(),
+                    velocity: self.velocity.get(&id).map(|&v| level::vector_to_pixels(v)),
+                    flags,
+                    mass: self.mass.get(&id).cloned(),
+                    priority: self.priority.get(&id).cloned(),
+                    path: self.kinematic.get(&id).map(|path| PathDesc {
+                        waypoints: path.waypoints()
+                            .iter()
+                            .map(|&waypoint| level::vector_to_pixels(waypoint))
+                            .collect(),
+                        speed: path.speed().to_pixels_f64(),
+                    }),
+                })
+            })
+            .collect();
+        Level {
+            size: level::vector_to_pixels(self.quad_tree.size()),
+            entities,
+            camera_bounds: self.camera_bounds.map(|bounds| CameraBounds {
+                position: level::vector_to_pixels(bounds.top_left()),
+                size: level::vector_to_pixels(bounds.size()),
+            }),
+        }
+    }
+    /// The position of an entity between the previous tick (0) and the current tick (1).
+    pub fn interpolated_position(
+        &self,
+        id: EntityId,
+        interpolation: f32,
+    ) -> Option<Vector2<SubPixelI64>> {
+        let interpolation = interpolation.max(0.).min(1.);
+        let denominator = SubPixelI64::new(sub_pixel_i64::SUB_PIXELS_PER_PIXEL);
+        let numerator =
+            SubPixelI64::new((interpolation * sub_pixel_i64::SUB_PIXELS_PER_PIXEL as f32) as i64);
+        self.position.get(&id).map(|&position| match self.previous_position.get(&id) {
+            Some(&previous) => previous + (position - previous) * numerator / denominator,
+            None => position,
+        })
+    }
+    pub fn interpolated_aabb(
+        &self,
+        id: EntityId,
+        interpolation: f32,
+    ) -> Option<Aabb<SubPixelI64>> {
+        match (self.shape.get(&id), self.interpolated_position(id, interpolation)) {
+            (Some(shape), Some(position)) => Some(shape.aabb(position)),
+            _ => None,
+        }
+    }
+    pub fn render_updates(&self) -> impl Iterator<Item = RenderUpdate<'_>> + '_ {
+        self.interpolated_render_updates(1.)
     }
-    pub fn render_updates(&self) -> impl Iterator<Item = RenderUpdate> {
-        let position = &self.position;
-        position.iter().filter_map(move |(id, &position)| {
+    /// Render updates with positions interpolated between the previous tick (0) and the
+    /// current tick (1), in no particular order.
+    pub fn interpolated_render_updates(
+        &self,
+        interpolation: f32,
+    ) -> impl Iterator<Item = RenderUpdate<'_>> + '_ {
+        self.position.keys().filter_map(move |id| {
+            let position = self.interpolated_position(*id, interpolation)?;
             self.shape.get(id).and_then(|shape| {
                 self.colour.get(id).map(|&colour| RenderUpdate {
+                    id: *id,
                     position,
                     shape,
                     colour,
+                    layer: self.layer.get(id).cloned().unwrap_or(0),
+                    line_thickness: self.line_thickness
+                        .get(id)
+                        .cloned()
+                        .unwrap_or(DEFAULT_LINE_THICKNESS),
+                    outline: self.outline.get(id).cloned(),
                 })
             })
         })
     }
+    pub fn draw<D: DrawTarget + ?Sized>(&self, target: &mut D) {
+        self.draw_interpolated(1., target);
+    }
+    /// Draws entities in increasing order of layer, and in order of id within a layer.
+    pub fn draw_interpolated<D: DrawTarget + ?Sized>(&self, interpolation: f32, target: &mut D) {
+        let mut updates = self.interpolated_render_updates(interpolation)
+            .collect::<Vec<_>>();
+        updates.sort_by_key(|update| (update.layer, update.id));
+        for update in updates.iter() {
+            update.draw(target);
+        }
+    }
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            state: self.clone(),
+        }
+    }
+    /// Overwrites an existing snapshot, reusing its allocations.
+    pub fn save_snapshot(&self, snapshot: &mut Snapshot) {
+        snapshot.state.clone_from(self);
+    }
+    pub fn restore_snapshot(&mut self, snapshot: &Snapshot) {
+        self.clone_from(&snapshot.state);
+    }
+    /// A hash of the position, velocity and shape of every entity. Entities are visited in
+    /// id order so the result doesn't depend on hash map iteration order, and only integer
+    /// state is hashed so it's identical across platforms.
+    pub fn checksum(&self) -> u64 {
+        let mut ids = self.position
+            .keys()
+            .chain(self.velocity.keys())
+            .chain(self.shape.keys())
+            .cloned()
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+        let mut hasher = FnvHasher::default();
+        for id in ids {
+            hasher.write_u32(id);
+            if let Some(&position) = self.position.get(&id) {
+                hasher.write_u8(b'p');
+                hash_vector(position, &mut hasher);
+            }
+            if let Some(&velocity) = self.velocity.get(&id) {
+                hasher.write_u8(b'v');
+                hash_vector(velocity, &mut hasher);
+            }
+            if let Some(shape) = self.shape.get(&id) {
+                hasher.write_u8(b's');
+                hash_shape(shape, &mut hasher);
+            }
+        }
+        hasher.finish()
+    }
     pub fn update(&mut self, input_model: &InputModel) {
         let player_id = self.player_id.expect("No player id");
         if let Some(velocity) = self.velocity.get_mut(&player_id) {
             *velocity = update_player_velocity(*velocity, input_model);
         }
-        for (id, velocity) in self.velocity.iter() {
-            if let Some(position) = position_after_movement(
-                *id,
-                &self.position,
-                &self.shape,
-                &self.quad_tree,
-                *velocity,
-            ) {
-                self.position.insert(*id, position);
+        self.previous_position.clone_from(&self.position);
+        self.crushed.clear();
+        self.collision_events.clear();
+        self.sensor_events.clear();
+        if let Some(ref mut movement_debug) = self.movement_debug {
+            movement_debug.clear();
+        }
+        self.update_kinematic();
+        for id in self.update_order(self.velocity.keys().cloned()) {
+            let velocity = self.velocity[&id];
+            if let Some(position) = self.position_after_movement(id, velocity, None) {
+                self.set_position(id, position);
+            }
+        }
+        self.record_adjacent_contacts();
+        self.contact_tracker.end_tick(&mut self.collision_events);
+        self.update_sensors();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axis_aligned_rect::AxisAlignedRect;
+    use contact::{ContactPhase, SensorPhase};
+    use test::Bencher;
+
+    fn many_entities(num_entities: usize) -> GameState {
+        let mut game_state = GameState::new(vec2(2048., 2048.));
+        game_state.init_demo();
+        for i in 0..num_entities {
+            let id = game_state.add_entity(
+                sub_pixel_i64::vector_from_f32_pixel(vec2(
+                    (i % 32) as f32 * 64.,
+                    256. + (i / 32) as f32 * 48.,
+                )),
+                Shape::AxisAlignedRect(AxisAlignedRect::new(vec2(
+                    SubPixelI64::new_pixels_f32(16.),
+                    SubPixelI64::new_pixels_f32(16.),
+                ))),
+                [1., 1., 1., 1.],
+            );
+            if i % 2 == 0 {
+                game_state.velocity.insert(
+                    id,
+                    vec2(SubPixelI64::new_pixels_f32(1.), Zero::zero()),
+                );
+            }
+        }
+        game_state
+    }
+
+    #[test]
+    fn restore_snapshot() {
+        let mut game_state = many_entities(100);
+        let snapshot = game_state.snapshot();
+        let checksum = game_state.checksum();
+        let mut input_model = InputModel::default();
+        input_model.set_right(1.);
+        for _ in 0..10 {
+            game_state.update(&input_model);
+        }
+        let checksum_after_update = game_state.checksum();
+        assert_ne!(checksum, checksum_after_update);
+        game_state.restore_snapshot(&snapshot);
+        assert_eq!(game_state.checksum(), checksum);
+        for _ in 0..10 {
+            game_state.update(&input_model);
+        }
+        assert_eq!(game_state.checksum(), checksum_after_update);
+    }
+
+    #[test]
+    fn checksum_ignores_hash_map_layout() {
+        let game_state = many_entities(100);
+        let mut resized = game_state.clone();
+        resized.position.reserve(10000);
+        resized.velocity.reserve(10000);
+        resized.shape.reserve(10000);
+        assert_eq!(game_state.checksum(), resized.checksum());
+    }
+
+    #[test]
+    fn checksum_covers_velocity_and_shape() {
+        let game_state = many_entities(10);
+        let checksum = game_state.checksum();
+        let mut changed_velocity = game_state.clone();
+        changed_velocity
+            .velocity
+            .insert(0, vec2(SubPixelI64::new(1), Zero::zero()));
+        assert_ne!(changed_velocity.checksum(), checksum);
+        let mut changed_shape = game_state.clone();
+        changed_shape.shape.insert(
+            0,
+            Shape::AxisAlignedRect(AxisAlignedRect::new(vec2(
+                SubPixelI64::new(1),
+                SubPixelI64::new(1),
+            ))),
+        );
+        assert_ne!(changed_shape.checksum(), checksum);
+    }
+
+    #[test]
+    fn update_order_ignores_hash_map_layout() {
+        let mut game_state = many_entities(200);
+        let mut resized = game_state.clone();
+        resized.position.reserve(10000);
+        resized.velocity.reserve(10000);
+        let mut input_model = InputModel::default();
+        input_model.set_down(1.);
+        for _ in 0..60 {
+            game_state.update(&input_model);
+            resized.update(&input_model);
+            assert_eq!(game_state.checksum(), resized.checksum());
+        }
+    }
+
+    #[test]
+    fn level_round_trip() {
+        let mut game_state = GameState::new(vec2(960., 640.));
+        game_state.init_demo();
+        let level = game_state.to_level();
+        let mut loaded = GameState::new(vec2(0., 0.));
+        loaded.load_level(&level).unwrap();
+        assert_eq!(loaded.checksum(), game_state.checksum());
+        assert_eq!(loaded.to_level(), level);
+    }
+
+    #[test]
+    fn load_level_rejects_invalid_level() {
+        let mut game_state = GameState::new(vec2(960., 640.));
+        game_state.init_demo();
+        let checksum = game_state.checksum();
+        let mut level = game_state.to_level();
+        let line_segment = level
+            .entities
+            .iter_mut()
+            .find(|entity| match entity.shape {
+                ShapeDesc::LineSegment { .. } => true,
+                _ => false,
+            })
+            .unwrap();
+        line_segment.velocity = Some([1., 0.]);
+        match game_state.load_level(&level) {
+            Err(LevelError::Invalid(message)) => assert!(message.contains("can't move")),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(game_state.checksum(), checksum);
+    }
+
+    #[test]
+    fn reload_level() {
+        let mut game_state = GameState::new(vec2(960., 640.));
+        game_state.init_demo();
+        let player_id = game_state.player_id().unwrap();
+        let start = game_state.player_position().unwrap();
+        let mut input_model = InputModel::default();
+        input_model.set_down(1.);
+        for _ in 0..10 {
+            game_state.update(&input_model);
+        }
+        let moved = game_state.player_position().unwrap();
+        assert_ne!(moved, start);
+        let mut level = game_state.to_level();
+        level.entities[player_id as usize].position = level::vector_to_pixels(start);
+        let snapshot = game_state.reload_level(&level, true).unwrap();
+        assert_eq!(game_state.player_position(), Some(moved));
+        assert_eq!(game_state.interpolated_position(player_id, 0.), Some(moved));
+        game_state.restore_snapshot(&snapshot);
+        assert_eq!(game_state.player_position(), Some(start));
+        game_state.reload_level(&level, false).unwrap();
+        assert_eq!(game_state.player_position(), Some(start));
+
+        let checksum = game_state.checksum();
+        level.entities.clear();
+        assert!(game_state.reload_level(&level, true).is_err());
+        assert_eq!(game_state.checksum(), checksum);
+    }
+
+    #[test]
+    fn camera_bounds() {
+        let mut game_state = GameState::new(vec2(960., 640.));
+        game_state.init_demo();
+        assert_eq!(
+            game_state.camera_bounds(),
+            Aabb::new(vec2(Zero::zero(), Zero::zero()), level::vector_from_pixels([960., 640.]))
+        );
+        let mut level = game_state.to_level();
+        level.camera_bounds = Some(CameraBounds {
+            position: [0., -320.],
+            size: [1920., 960.],
+        });
+        game_state.load_level(&level).unwrap();
+        assert_eq!(
+            game_state.camera_bounds().top_left(),
+            level::vector_from_pixels([0., -320.])
+        );
+        assert_eq!(game_state.to_level(), level);
+    }
+
+    /// Records which kind of shape was drawn in which colour, in order.
+    #[derive(Default)]
+    struct DrawCalls(Vec<(&'static str, [f32; 4])>);
+
+    impl DrawTarget for DrawCalls {
+        fn axis_aligned_rect(&mut self, _: Vector2<f32>, _: Vector2<f32>, colour: [f32; 4]) {
+            self.0.push(("rect", colour));
+        }
+        fn line_segment(
+            &mut self,
+            _: Vector2<f32>,
+            _: Vector2<f32>,
+            _: f32,
+            colour: [f32; 4],
+        ) {
+            self.0.push(("line", colour));
+        }
+        fn convex_polygon(&mut self, _: &[Vector2<f32>], colour: [f32; 4]) {
+            self.0.push(("polygon", colour));
+        }
+        fn circle(&mut self, _: Vector2<f32>, _: f32, colour: [f32; 4]) {
+            self.0.push(("circle", colour));
+        }
+        fn filled_rect_outline(
+            &mut self,
+            _: Vector2<f32>,
+            _: Vector2<f32>,
+            _: f32,
+            colour: [f32; 4],
+        ) {
+            self.0.push(("outline", colour));
+        }
+    }
+
+    #[test]
+    fn draw_order_and_style() {
+        let level = "{
+            \"size\": [64, 64],
+            \"entities\": [
+                { \"position\": [0, 0], \"colour\": [1, 0, 0], \"alpha\": 0.5,
+                  \"layer\": 2, \"flags\": [\"player\"],
+                  \"shape\": { \"type\": \"axis_aligned_rect\", \"size\": [4, 4] } },
+                { \"position\": [0, 0], \"colour\": [0, 1, 0], \"line_thickness\": 4,
+                  \"shape\": { \"type\": \"line_segment\", \"start\": [0, 0], \"end\": [8, 0] } },
+                { \"position\": [0, 0], \"colour\": [0, 0, 1], \"layer\": -1, \"outline\": 1,
+                  \"shape\": { \"type\": \"axis_aligned_rect\", \"size\": [64, 64] } }
+            ]
+        }".parse::<Level>()
+            .unwrap();
+        let mut game_state = GameState::new(vec2(0., 0.));
+        game_state.load_level(&level).unwrap();
+        let mut calls = DrawCalls::default();
+        game_state.draw(&mut calls);
+        assert_eq!(
+            calls.0,
+            vec![
+                ("outline", [0., 0., 1., 1.]),
+                ("line", [0., 1., 0., 1.]),
+                ("rect", [1., 0., 0., 0.5]),
+            ]
+        );
+        assert_eq!(game_state.to_level(), level);
+    }
+
+    #[test]
+    fn slide_along_tile_map_without_catching() {
+        let level = "{
+            \"size\": [320, 320],
+            \"entities\": [
+                { \"position\": [0, 0], \"colour\": [1, 1, 1],
+                  \"shape\": { \"type\": \"tile_map\", \"tile_size\": [16, 16],
+                               \"rows\": [\"....................\", \"....................\",
+                                        \"####################\"] } },
+                { \"position\": [4, 16], \"colour\": [1, 0, 0], \"velocity\": [0, 0],
+                  \"flags\": [\"player\"],
+                  \"shape\": { \"type\": \"axis_aligned_rect\", \"size\": [8, 16] } }
+            ]
+        }".parse::<Level>()
+            .unwrap();
+        let mut game_state = GameState::new(vec2(320., 320.));
+        game_state.load_level(&level).unwrap();
+        let player_id = game_state.player_id.unwrap();
+        let mut input_model = InputModel::default();
+        input_model.set_right(1.);
+        input_model.set_down(1.);
+        for _ in 0..60 {
+            game_state.update(&input_model);
+        }
+        let position = game_state.position[&player_id];
+        assert!(position.x > SubPixelI64::new_pixels_f64(100.));
+        assert!(position.y <= SubPixelI64::new_pixels_f64(16.));
+    }
+
+    /// A box riding a platform which moves it into a wall, with the player out of the way.
+    fn carry_level(platform_waypoint: [f64; 2], wall_position: [f64; 2]) -> GameState {
+        let level = format!(
+            "{{
+                \"size\": [320, 320],
+                \"entities\": [
+                    {{ \"position\": [0, 100], \"colour\": [1, 1, 1],
+                      \"path\": {{ \"waypoints\": [[0, 100], {:?}], \"speed\": 2 }},
+                      \"shape\": {{ \"type\": \"axis_aligned_rect\", \"size\": [64, 16] }} }},
+                    {{ \"position\": [16, 84], \"colour\": [1, 1, 1], \"flags\": [\"pushable\"],
+                      \"shape\": {{ \"type\": \"axis_aligned_rect\", \"size\": [16, 16] }} }},
+                    {{ \"position\": {:?}, \"colour\": [1, 1, 1],
+                      \"shape\": {{ \"type\": \"axis_aligned_rect\", \"size\": [64, 8] }} }},
+                    {{ \"position\": [280, 280], \"colour\": [1, 0, 0], \"velocity\": [0, 0],
+                      \"flags\": [\"player\"],
+                      \"shape\": {{ \"type\": \"axis_aligned_rect\", \"size\": [8, 8] }} }}
+                ]
+            }}",
+            platform_waypoint, wall_position
+        );
+        let mut game_state = GameState::new(vec2(320., 320.));
+        game_state.load_level(&level.parse().unwrap()).unwrap();
+        game_state
+    }
+
+    #[test]
+    fn carried_sideways_into_wall_stops() {
+        // the wall is level with the box but above the platform
+        let mut game_state = carry_level([200., 100.], [48., 88.]);
+        let box_id = 1;
+        for _ in 0..30 {
+            game_state.update(&InputModel::default());
+            assert!(game_state.crushed().is_empty());
+        }
+        let position = game_state.position[&box_id];
+        assert!(position.x <= SubPixelI64::new_pixels_f64(32.));
+        assert!(position.x > SubPixelI64::new_pixels_f64(31.));
+        assert_eq!(position.y, SubPixelI64::new_pixels_f64(84.));
+    }
+
+    /// The gap left between entities in `push_row`, which is what's left between a pusher
+    /// and the entity it pushes.
+    const PUSH_GAP: f64 = 1. / 256.;
+
+    /// The player (id 0) with a row of 8 pixel pushable boxes to its right, ids 1 and up,
+    /// and a wall at `wall_x`.
+    fn push_row(
+        player_mass: Option<i64>,
+        box_masses: &[Option<i64>],
+        wall_x: f64,
+    ) -> GameState {
+        let mass = |mass: Option<i64>| {
+            mass.map_or(String::new(), |mass| format!(", \"mass\": {}", mass))
+        };
+        let mut entities = vec![format!(
+            "{{ \"position\": [0, 100], \"colour\": [1, 0, 0], \"velocity\": [0, 0],
+                \"flags\": [\"player\"]{},
+                \"shape\": {{ \"type\": \"axis_aligned_rect\", \"size\": [8, 8] }} }}",
+            mass(player_mass)
+        )];
+        for (i, &box_mass) in box_masses.iter().enumerate() {
+            entities.push(format!(
+                "{{ \"position\": [{}, 100], \"colour\": [1, 1, 1],
+                    \"flags\": [\"pushable\"]{},
+                    \"shape\": {{ \"type\": \"axis_aligned_rect\", \"size\": [8, 8] }} }}",
+                (8. + PUSH_GAP) * (i + 1) as f64,
+                mass(box_mass)
+            ));
+        }
+        entities.push(format!(
+            "{{ \"position\": [{}, 80], \"colour\": [1, 1, 1],
+                \"shape\": {{ \"type\": \"axis_aligned_rect\", \"size\": [8, 48] }} }}",
+            wall_x
+        ));
+        let level = format!(
+            "{{ \"size\": [320, 320], \"entities\": [{}] }}",
+            entities.join(", ")
+        );
+        let mut game_state = GameState::new(vec2(320., 320.));
+        game_state.load_level(&level.parse().unwrap()).unwrap();
+        game_state
+    }
+
+    fn push_right(game_state: &mut GameState, ticks: usize) {
+        let mut input_model = InputModel::default();
+        input_model.set_right(1.);
+        for _ in 0..ticks {
+            game_state.update(&input_model);
+        }
+    }
+
+    /// Asserts that an entity in a `push_row` has moved this many pixels, give or take
+    /// the gaps between entities and the padding left by sliding.
+    fn assert_moved(game_state: &GameState, id: EntityId, pixels: f64) {
+        let x = game_state.position[&id].x.to_pixels_f64();
+        let expected = (8. + PUSH_GAP) * id as f64 + pixels;
+        assert!(
+            (x - expected).abs() <= 0.125,
+            "entity {} is at {} instead of {}",
+            id,
+            x,
+            expected
+        );
+    }
+
+    #[test]
+    fn push_chain() {
+        let mut game_state = push_row(None, &[None, None], 300.);
+        push_right(&mut game_state, 2);
+        for id in 0..3 {
+            assert_moved(&game_state, id, 8.);
+        }
+    }
+
+    #[test]
+    fn pushed_into_wall_stops_pusher() {
+        let mut game_state = push_row(None, &[None, None], 40.);
+        push_right(&mut game_state, 10);
+        for id in 0..3 {
+            assert_moved(&game_state, id, 16.);
+        }
+        assert!(game_state.position[&2].x < SubPixelI64::new_pixels_f64(32.));
+    }
+
+    #[test]
+    fn push_chain_depth_limit() {
+        let mut game_state = push_row(None, &[None; MAX_PUSH_DEPTH + 1], 300.);
+        push_right(&mut game_state, 1);
+        for id in 0..MAX_PUSH_DEPTH as EntityId + 2 {
+            assert_moved(&game_state, id, 4.);
+        }
+        let mut game_state = push_row(None, &[None; MAX_PUSH_DEPTH + 2], 300.);
+        push_right(&mut game_state, 1);
+        for id in 0..MAX_PUSH_DEPTH as EntityId + 3 {
+            assert_moved(&game_state, id, 0.);
+        }
+    }
+
+    #[test]
+    fn movement_iteration_limit() {
+        // the player falls into a funnel, sliding from side to side
+        let level = "{
+            \"size\": [320, 320],
+            \"entities\": [
+                { \"position\": [96, 0], \"colour\": [1, 0, 0], \"velocity\": [0, 0],
+                  \"flags\": [\"player\"],
+                  \"shape\": { \"type\": \"axis_aligned_rect\", \"size\": [8, 8] } },
+                { \"position\": [0, 0], \"colour\": [1, 1, 1],
+                  \"shape\": { \"type\": \"line_segment\",
+                               \"start\": [0, 0], \"end\": [100, 200] } },
+                { \"position\": [0, 0], \"colour\": [1, 1, 1],
+                  \"shape\": { \"type\": \"line_segment\",
+                               \"start\": [200, 0], \"end\": [100, 200] } }
+            ]
+        }";
+        let mut game_state = GameState::new(vec2(320., 320.));
+        game_state.load_level(&level.parse().unwrap()).unwrap();
+        game_state.set_movement_debug(true);
+        let movement = level::vector_from_pixels([3., 300.]);
+        let position = game_state.position_after_movement(0, movement, None).unwrap();
+        assert_eq!(game_state.movement_debug().len(), MAX_MOVEMENT_ITERATIONS);
+        assert!(position.y < SubPixelI64::new_pixels_f64(200.));
+    }
+
+    #[test]
+    fn only_heavier_entities_slow_pushing() {
+        for &(player_mass, box_mass, expected) in &[
+            (None, Some(4), 8.),
+            (Some(1), None, 8.),
+            (Some(2), Some(1), 8.),
+            (Some(2), Some(2), 8.),
+            (Some(1), Some(2), 4.),
+            (Some(1), Some(4), 2.),
+        ] {
+            let mut game_state = push_row(player_mass, &[box_mass], 300.);
+            push_right(&mut game_state, 2);
+            assert_moved(&game_state, 0, expected);
+            assert_moved(&game_state, 1, expected);
+        }
+    }
+
+    /// The player (id 0) moving right towards a sensor (id 1) and a wall (id 2), followed
+    /// by any other entities.
+    fn sensor_level(sensor: &str, wall_x: f64, others: &str) -> GameState {
+        let level = format!(
+            "{{
+                \"size\": [320, 320],
+                \"entities\": [
+                    {{ \"position\": [0, 100], \"colour\": [1, 0, 0], \"velocity\": [0, 0],
+                      \"flags\": [\"player\"],
+                      \"shape\": {{ \"type\": \"axis_aligned_rect\", \"size\": [8, 8] }} }},
+                    {{ \"colour\": [0, 1, 0], \"flags\": [\"sensor\"], {} }},
+                    {{ \"position\": [{}, 80], \"colour\": [1, 1, 1],
+                      \"shape\": {{ \"type\": \"axis_aligned_rect\", \"size\": [8, 48] }} }}
+                    {}
+                ]
+            }}",
+            sensor, wall_x, others
+        );
+        let mut game_state = GameState::new(vec2(320., 320.));
+        game_state.load_level(&level.parse().unwrap()).unwrap();
+        game_state
+    }
+
+    fn sensor_phases(game_state: &GameState) -> Vec<(SensorPhase, EntityId, EntityId)> {
+        game_state
+            .sensor_events()
+            .iter()
+            .map(|event| (event.phase, event.sensor_id, event.entity_id))
+            .collect()
+    }
+
+    #[test]
+    fn entering_and_leaving_sensor() {
+        let sensor = "\"position\": [20, 96],
+            \"shape\": { \"type\": \"axis_aligned_rect\", \"size\": [16, 16] }";
+        let mut game_state = sensor_level(sensor, 300., "");
+        let (player_id, sensor_id) = (0, 1);
+        let mut input_model = InputModel::default();
+        input_model.set_right(1.);
+        let mut events = Vec::new();
+        for tick in 0..20 {
+            game_state.update(&input_model);
+            for (phase, event_sensor_id, entity_id) in sensor_phases(&game_state) {
+                assert_eq!((event_sensor_id, entity_id), (sensor_id, player_id));
+                events.push((tick, phase));
             }
         }
+        // the player moves 4 pixels a tick, and overlaps the sensor from 16 to 32
+        // the player moves 4 pixels a tick and touches the sensor while its x is from 12
+        // to 36, and it exits on the first tick which starts clear of the sensor
+        assert_eq!(
+            events,
+            vec![(2, SensorPhase::Entered), (10, SensorPhase::Exited)]
+        );
+    }
+
+    #[test]
+    fn sensors_dont_block_or_break_ties() {
+        // the sensor's left edge is level with the wall's, and its id is lower
+        let sensor = "\"position\": [40, 96],
+            \"shape\": { \"type\": \"axis_aligned_rect\", \"size\": [16, 16] }";
+        let mut game_state = sensor_level(sensor, 40., "");
+        game_state.set_movement_debug(true);
+        let wall_id = 2;
+        let mut input_model = InputModel::default();
+        input_model.set_right(1.);
+        let mut collided = false;
+        for _ in 0..20 {
+            game_state.update(&input_model);
+            for step in game_state.movement_debug() {
+                if let Some(ref collision) = step.collision {
+                    assert_eq!(collision.entity_id, wall_id);
+                    collided = true;
+                }
+            }
+        }
+        assert!(collided);
+        let position = game_state.position[&0];
+        assert!(position.x > SubPixelI64::new_pixels_f64(31.));
+        assert!(position.x <= SubPixelI64::new_pixels_f64(32.));
+    }
+
+    #[test]
+    fn fast_entity_passing_through_sensor() {
+        // the mover starts and ends the tick clear of the sensor
+        let sensor = "\"position\": [20, 196],
+            \"shape\": { \"type\": \"axis_aligned_rect\", \"size\": [1, 16] }";
+        let mover = ", { \"position\": [0, 200], \"colour\": [1, 1, 1],
+            \"velocity\": [32, 0], \"shape\": { \"type\": \"axis_aligned_rect\", \"size\": [8, 8] } }";
+        let mut game_state = sensor_level(sensor, 300., mover);
+        let (sensor_id, mover_id) = (1, 3);
+        game_state.update(&InputModel::default());
+        assert_eq!(
+            game_state.position[&mover_id].x,
+            SubPixelI64::new_pixels_f64(32.)
+        );
+        assert_eq!(
+            sensor_phases(&game_state),
+            vec![(SensorPhase::Entered, sensor_id, mover_id)]
+        );
+        game_state.update(&InputModel::default());
+        assert_eq!(
+            sensor_phases(&game_state),
+            vec![(SensorPhase::Exited, sensor_id, mover_id)]
+        );
+    }
+
+    fn contact_phases(game_state: &GameState, a: EntityId, b: EntityId) -> Vec<ContactPhase> {
+        game_state
+            .collision_events()
+            .iter()
+            .filter(|event| {
+                (event.moving_id, event.other_id) == (a, b)
+                    || (event.moving_id, event.other_id) == (b, a)
+            })
+            .map(|event| event.phase)
+            .collect()
+    }
+
+    #[test]
+    fn resting_contact_begins_persists_and_ends() {
+        let level = "{
+            \"size\": [320, 320],
+            \"entities\": [
+                { \"position\": [100, 92], \"colour\": [1, 0, 0], \"velocity\": [0, 0],
+                  \"flags\": [\"player\"],
+                  \"shape\": { \"type\": \"axis_aligned_rect\", \"size\": [8, 8] } },
+                { \"position\": [0, 100], \"colour\": [1, 1, 1],
+                  \"shape\": { \"type\": \"axis_aligned_rect\", \"size\": [320, 8] } }
+            ]
+        }";
+        let mut game_state = GameState::new(vec2(320., 320.));
+        game_state.load_level(&level.parse().unwrap()).unwrap();
+        let (player_id, floor_id) = (0, 1);
+        game_state.update(&InputModel::default());
+        assert_eq!(
+            contact_phases(&game_state, player_id, floor_id),
+            vec![ContactPhase::Began]
+        );
+        for _ in 0..3 {
+            game_state.update(&InputModel::default());
+            assert_eq!(
+                contact_phases(&game_state, player_id, floor_id),
+                vec![ContactPhase::Persisted]
+            );
+        }
+        let mut input_model = InputModel::default();
+        input_model.set_up(1.);
+        game_state.update(&input_model);
+        assert_eq!(
+            contact_phases(&game_state, player_id, floor_id),
+            vec![ContactPhase::Ended]
+        );
+        game_state.update(&input_model);
+        assert!(contact_phases(&game_state, player_id, floor_id).is_empty());
+    }
+
+    #[test]
+    fn carried_contact_persists() {
+        let mut game_state = carry_level([200., 100.], [0., 0.]);
+        let (platform_id, box_id) = (0, 1);
+        game_state.update(&InputModel::default());
+        assert_eq!(
+            contact_phases(&game_state, box_id, platform_id),
+            vec![ContactPhase::Began]
+        );
+        for _ in 0..30 {
+            game_state.update(&InputModel::default());
+            assert_eq!(
+                contact_phases(&game_state, box_id, platform_id),
+                vec![ContactPhase::Persisted]
+            );
+        }
+    }
+
+    #[test]
+    fn carried_up_into_ceiling_is_crushed() {
+        let mut game_state = carry_level([0., 0.], [0., 60.]);
+        let box_id = 1;
+        let mut crushed = false;
+        for _ in 0..30 {
+            game_state.update(&InputModel::default());
+            crushed |= game_state.crushed().contains(&box_id);
+        }
+        assert!(crushed);
+    }
+
+    #[bench]
+    fn save_snapshot_1000_entities(b: &mut Bencher) {
+        let game_state = many_entities(1000);
+        let mut snapshot = game_state.snapshot();
+        b.iter(|| game_state.save_snapshot(&mut snapshot));
+    }
+
+    #[bench]
+    fn restore_snapshot_1000_entities(b: &mut Bencher) {
+        let mut game_state = many_entities(1000);
+        let snapshot = game_state.snapshot();
+        b.iter(|| game_state.restore_snapshot(&snapshot));
     }
 }
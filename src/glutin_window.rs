@@ -2,6 +2,7 @@ use gfx;
 use gfx_device_gl;
 use gfx_window_glutin;
 use glutin;
+use glutin::GlContext;
 
 use graphics::formats;
 
@@ -23,10 +24,7 @@ pub struct GlutinWindow {
 
 impl GlutinWindow {
     pub fn new(width: u32, height: u32) -> Self {
-        let builder = glutin::WindowBuilder::new()
-            .with_dimensions(width, height)
-            .with_min_dimensions(width, height)
-            .with_max_dimensions(width, height);
+        let builder = glutin::WindowBuilder::new().with_dimensions(width, height);
         let events_loop = glutin::EventsLoop::new();
         let context = glutin::ContextBuilder::new().with_vsync(true);
         let (window, device, mut factory, render_target_view, depth_stencil_view) =
@@ -49,3 +47,22 @@ impl GlutinWindow {
         }
     }
 }
+
+/// Call on `glutin::WindowEvent::Resized`. Resizes the GL context and replaces the views
+/// with ones of the new size.
+pub fn resize(
+    window: &glutin::GlWindow,
+    width: u32,
+    height: u32,
+    render_target_view: &mut gfx::handle::RenderTargetView<
+        gfx_device_gl::Resources,
+        formats::Colour,
+    >,
+    depth_stencil_view: &mut gfx::handle::DepthStencilView<
+        gfx_device_gl::Resources,
+        formats::Depth,
+    >,
+) {
+    window.resize(width, height);
+    gfx_window_glutin::update_views(window, render_target_view, depth_stencil_view);
+}
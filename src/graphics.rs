@@ -12,28 +12,51 @@ mod consts {
 
 mod dimensions {
     use super::formats;
-    use cgmath::{Vector2, vec2};
+    use vector::{Vector2, vec2};
     use gfx;
+    use viewport::Viewport;
     pub fn rtv_dimensions<R: gfx::Resources>(
         rtv: &gfx::handle::RenderTargetView<R, formats::Colour>,
     ) -> Vector2<u16> {
         let (width, height, _, _) = rtv.get_dimensions();
         vec2(width, height)
     }
+    /// The viewport's clip rectangle, limited to the window. GL scissor rectangles are
+    /// measured from the bottom left of the window.
+    pub fn scissor(viewport: &Viewport, window_size: Vector2<u16>) -> gfx::Rect {
+        let window_size = vec2(window_size.x as f32, window_size.y as f32);
+        let (top_left, size) = viewport.clip.unwrap_or((vec2(0., 0.), window_size));
+        let bottom_right = top_left + size;
+        let left = top_left.x.max(0.);
+        let top = top_left.y.max(0.);
+        let right = bottom_right.x.min(window_size.x).max(left);
+        let bottom = bottom_right.y.min(window_size.y).max(top);
+        gfx::Rect {
+            x: left as u16,
+            y: (window_size.y - bottom) as u16,
+            w: (right - left) as u16,
+            h: (bottom - top) as u16,
+        }
+    }
 }
 
 mod buffer_types {
-    use cgmath::Vector2;
+    use camera::Camera;
+    use vector::Vector2;
     use gfx;
+    use viewport::Viewport;
     gfx_vertex_struct!(QuadCorners {
         corner_zero_to_one: [f32; 2] = "a_CornerZeroToOne",
     });
     gfx_constant_struct!(WindowProperties {
         window_size_in_pixels: [f32; 2] = "u_WindowSizeInPixels",
+        world_to_window_scale: [f32; 2] = "u_WorldToWindowScale",
+        world_to_window_offset: [f32; 2] = "u_WorldToWindowOffset",
     });
     pub fn update_window_properties<R, C>(
         properties_buffer: &gfx::handle::Buffer<R, WindowProperties>,
         window_size: Vector2<u16>,
+        viewport: &Viewport,
         encoder: &mut gfx::Encoder<R, C>,
     ) where
         R: gfx::Resources,
@@ -41,6 +64,26 @@ mod buffer_types {
     {
         let properties = WindowProperties {
             window_size_in_pixels: [window_size.x as f32, window_size.y as f32],
+            world_to_window_scale: viewport.scale.into(),
+            world_to_window_offset: viewport.offset.into(),
+        };
+        encoder.update_constant_buffer(properties_buffer, &properties);
+    }
+    gfx_constant_struct!(CameraProperties {
+        top_left: [f32; 2] = "u_CameraTopLeft",
+        zoom: [f32; 2] = "u_CameraZoom",
+    });
+    pub fn update_camera_properties<R, C>(
+        properties_buffer: &gfx::handle::Buffer<R, CameraProperties>,
+        camera: &Camera,
+        encoder: &mut gfx::Encoder<R, C>,
+    ) where
+        R: gfx::Resources,
+        C: gfx::CommandBuffer<R>,
+    {
+        let properties = CameraProperties {
+            top_left: camera.top_left().into(),
+            zoom: [camera.zoom(), camera.zoom()],
         };
         encoder.update_constant_buffer(properties_buffer, &properties);
     }
@@ -101,15 +144,24 @@ mod buffer_alloc {
     {
         factory.create_constant_buffer(1)
     }
+    pub fn create_camera_property_buffer<R, F>(
+        factory: &mut F,
+    ) -> gfx::handle::Buffer<R, buffer_types::CameraProperties>
+    where
+        R: gfx::Resources,
+        F: gfx::Factory<R> + gfx::traits::FactoryExt<R>,
+    {
+        factory.create_constant_buffer(1)
+    }
 }
 
 mod instance_renderer {
     use super::buffer_alloc;
     use super::buffer_types;
     use super::formats;
-    use super::InstanceWriter;
     use gfx;
-    const MAX_NUM_INSTANCES: usize = 1024;
+    /// Instance buffers start this big, and double whenever a frame needs more.
+    const INITIAL_NUM_INSTANCES: usize = 1024;
     pub struct ShaderBytes {
         pub vertex: &'static [u8],
         pub fragment: &'static [u8],
@@ -123,22 +175,31 @@ mod instance_renderer {
             corners: gfx::handle::Buffer<R, buffer_types::QuadCorners>,
             instances: gfx::handle::Buffer<R, Self::Instance>,
             properties: gfx::handle::Buffer<R, buffer_types::WindowProperties>,
+            camera: gfx::handle::Buffer<R, buffer_types::CameraProperties>,
+            scissor: gfx::Rect,
             target: gfx::handle::RenderTargetView<R, formats::Colour>,
         ) -> Self;
         fn new_pipe() -> Self::PipeInit;
         fn instances(&self) -> &gfx::handle::Buffer<R, Self::Instance>;
+        fn instances_mut(&mut self) -> &mut gfx::handle::Buffer<R, Self::Instance>;
+        fn scissor_mut(&mut self) -> &mut gfx::Rect;
+        fn target_mut(&mut self) -> &mut gfx::handle::RenderTargetView<R, formats::Colour>;
         fn shader_bytes() -> ShaderBytes;
     }
     pub struct Renderer<R: gfx::Resources, D: PipelineData<R>> {
         bundle: gfx::Bundle<R, D>,
-        num_quads: usize,
+        instances: Vec<D::Instance>,
         instances_upload: gfx::handle::Buffer<R, D::Instance>,
+        capacity: usize,
+        high_water_mark: usize,
     }
 
     impl<R: gfx::Resources, D: PipelineData<R>> Renderer<R, D> {
         pub fn new<F>(
             colour_rtv: &gfx::handle::RenderTargetView<R, formats::Colour>,
             window_properties: &gfx::handle::Buffer<R, buffer_types::WindowProperties>,
+            camera_properties: &gfx::handle::Buffer<R, buffer_types::CameraProperties>,
+            scissor: gfx::Rect,
             factory: &mut F,
         ) -> Self
         where
@@ -157,55 +218,112 @@ mod instance_renderer {
 
             let (instances, instances_upload) =
                 buffer_alloc::create_instance_and_upload_buffers(
-                    MAX_NUM_INSTANCES,
+                    INITIAL_NUM_INSTANCES,
                     factory,
                 ).expect("Failed to create buffers");
             let data = <D as PipelineData<R>>::new_data(
                 quad_corners_buf,
                 instances,
                 window_properties.clone(),
+                camera_properties.clone(),
+                scissor,
                 colour_rtv.clone(),
             );
             let bundle = gfx::pso::bundle::Bundle::new(slice, pso, data);
 
             Self {
                 bundle,
-                num_quads: 0,
+                instances: Vec::new(),
                 instances_upload,
+                capacity: INITIAL_NUM_INSTANCES,
+                high_water_mark: 0,
             }
         }
 
-        pub fn instance_writer<F>(
+        /// The most instances drawn in a single frame so far.
+        pub fn high_water_mark(&self) -> usize {
+            self.high_water_mark
+        }
+
+        /// The number of instances the buffers currently have room for.
+        pub fn capacity(&self) -> usize {
+            self.capacity
+        }
+
+        pub fn set_target(
             &mut self,
-            factory: &mut F,
-        ) -> InstanceWriter<R, D::Instance>
+            colour_rtv: &gfx::handle::RenderTargetView<R, formats::Colour>,
+        ) {
+            *self.bundle.data.target_mut() = colour_rtv.clone();
+        }
+
+        pub fn set_scissor(&mut self, scissor: gfx::Rect) {
+            *self.bundle.data.scissor_mut() = scissor;
+        }
+
+        /// Clears the instances from the last frame, and returns the list to add this
+        /// frame's instances to.
+        pub fn begin_frame(&mut self) -> &mut Vec<D::Instance> {
+            self.instances.clear();
+            &mut self.instances
+        }
+
+        fn grow<F>(&mut self, factory: &mut F)
         where
             F: gfx::Factory<R> + gfx::traits::FactoryExt<R>,
         {
-            let writer = factory
-                .write_mapping(&self.instances_upload)
-                .expect("Failed to map upload buffer");
-            self.num_quads = 0;
-            InstanceWriter {
-                num_instances: &mut self.num_quads,
-                bundle_slice_instances: &mut self.bundle.slice.instances,
-                writer,
+            let mut capacity = self.capacity;
+            while capacity < self.instances.len() {
+                capacity *= 2;
             }
+            let (instances, instances_upload) =
+                buffer_alloc::create_instance_and_upload_buffers(capacity, factory)
+                    .expect("Failed to create buffers");
+            *self.bundle.data.instances_mut() = instances;
+            self.instances_upload = instances_upload;
+            self.capacity = capacity;
         }
 
-        pub fn encode<C>(&self, encoder: &mut gfx::Encoder<R, C>)
+        /// Writes this frame's instances to the upload buffer, first replacing the
+        /// buffers with bigger ones if they don't fit.
+        pub fn upload<F>(&mut self, factory: &mut F)
         where
+            F: gfx::Factory<R> + gfx::traits::FactoryExt<R>,
+        {
+            if self.instances.len() > self.capacity {
+                self.grow(factory);
+            }
+            self.high_water_mark = self.high_water_mark.max(self.instances.len());
+            {
+                let mut writer = factory
+                    .write_mapping(&self.instances_upload)
+                    .expect("Failed to map upload buffer");
+                for (dst, src) in writer.iter_mut().zip(self.instances.iter()) {
+                    *dst = *src;
+                }
+            }
+        }
+
+        /// Draws the instances in `start..end`. They're copied to the start of the
+        /// instance buffer first, since base instances aren't available everywhere.
+        pub fn encode<C>(
+            &mut self,
+            start: usize,
+            end: usize,
+            encoder: &mut gfx::Encoder<R, C>,
+        ) where
             C: gfx::CommandBuffer<R>,
         {
             encoder
                 .copy_buffer(
                     &self.instances_upload,
                     self.bundle.data.instances(),
+                    start,
                     0,
-                    0,
-                    self.num_quads,
+                    end - start,
                 )
                 .expect("Failed to copy instances");
+            self.bundle.slice.instances = Some(((end - start) as u32, 0));
             self.bundle.encode(encoder);
         }
     }
@@ -220,13 +338,15 @@ pub mod quad {
     gfx_vertex_struct!(Instance {
         position_of_top_left_in_pixels: [f32; 2] = "i_PositionOfTopLeftInPixels",
         dimensions_in_pixels: [f32; 2] = "i_DimensionsInPixels",
-        colour: [f32; 3] = "i_Colour",
+        colour: [f32; 4] = "i_Colour",
     });
 
     gfx_pipeline!(pipe {
         quad_corners: gfx::VertexBuffer<buffer_types::QuadCorners> = (),
         instances: gfx::InstanceBuffer<Instance> = (),
         properties: gfx::ConstantBuffer<buffer_types::WindowProperties> = "WindowProperties",
+        camera: gfx::ConstantBuffer<buffer_types::CameraProperties> = "CameraProperties",
+        scissor: gfx::Scissor = (),
         target: gfx::BlendTarget<formats::Colour> =
             ("Target", gfx::state::ColorMask::all(), gfx::preset::blend::ALPHA),
     });
@@ -237,12 +357,16 @@ pub mod quad {
             corners: gfx::handle::Buffer<R, buffer_types::QuadCorners>,
             instances: gfx::handle::Buffer<R, Self::Instance>,
             properties: gfx::handle::Buffer<R, buffer_types::WindowProperties>,
+            camera: gfx::handle::Buffer<R, buffer_types::CameraProperties>,
+            scissor: gfx::Rect,
             target: gfx::handle::RenderTargetView<R, formats::Colour>,
         ) -> Self {
             pipe::Data {
                 quad_corners: corners,
                 instances,
                 properties,
+                camera,
+                scissor,
                 target,
             }
         }
@@ -252,6 +376,15 @@ pub mod quad {
         fn instances(&self) -> &gfx::handle::Buffer<R, Self::Instance> {
             &self.instances
         }
+        fn instances_mut(&mut self) -> &mut gfx::handle::Buffer<R, Self::Instance> {
+            &mut self.instances
+        }
+        fn scissor_mut(&mut self) -> &mut gfx::Rect {
+            &mut self.scissor
+        }
+        fn target_mut(&mut self) -> &mut gfx::handle::RenderTargetView<R, formats::Colour> {
+            &mut self.target
+        }
         fn shader_bytes() -> ShaderBytes {
             ShaderBytes {
                 vertex: include_bytes!("shaders/quad/shader.150.vert"),
@@ -272,13 +405,16 @@ pub mod line_segment {
     gfx_vertex_struct!(Instance {
         start: [f32; 2] = "i_Start",
         end: [f32; 2] = "i_End",
-        colour: [f32; 3] = "i_Colour",
+        thickness: f32 = "i_Thickness",
+        colour: [f32; 4] = "i_Colour",
     });
 
     gfx_pipeline!(pipe {
         quad_corners: gfx::VertexBuffer<buffer_types::QuadCorners> = (),
         instances: gfx::InstanceBuffer<Instance> = (),
         properties: gfx::ConstantBuffer<buffer_types::WindowProperties> = "WindowProperties",
+        camera: gfx::ConstantBuffer<buffer_types::CameraProperties> = "CameraProperties",
+        scissor: gfx::Scissor = (),
         target: gfx::BlendTarget<formats::Colour> =
             ("Target", gfx::state::ColorMask::all(), gfx::preset::blend::ALPHA),
     });
@@ -290,12 +426,16 @@ pub mod line_segment {
             corners: gfx::handle::Buffer<R, buffer_types::QuadCorners>,
             instances: gfx::handle::Buffer<R, Self::Instance>,
             properties: gfx::handle::Buffer<R, buffer_types::WindowProperties>,
+            camera: gfx::handle::Buffer<R, buffer_types::CameraProperties>,
+            scissor: gfx::Rect,
             target: gfx::handle::RenderTargetView<R, formats::Colour>,
         ) -> Self {
             pipe::Data {
                 quad_corners: corners,
                 instances,
                 properties,
+                camera,
+                scissor,
                 target,
             }
         }
@@ -305,6 +445,15 @@ pub mod line_segment {
         fn instances(&self) -> &gfx::handle::Buffer<R, Self::Instance> {
             &self.instances
         }
+        fn instances_mut(&mut self) -> &mut gfx::handle::Buffer<R, Self::Instance> {
+            &mut self.instances
+        }
+        fn scissor_mut(&mut self) -> &mut gfx::Rect {
+            &mut self.scissor
+        }
+        fn target_mut(&mut self) -> &mut gfx::handle::RenderTargetView<R, formats::Colour> {
+            &mut self.target
+        }
         fn shader_bytes() -> ShaderBytes {
             ShaderBytes {
                 vertex: include_bytes!("shaders/line_segment/shader.150.vert"),
@@ -316,92 +465,372 @@ pub mod line_segment {
     pub type Renderer<R> = instance_renderer::Renderer<R, pipe::Data<R>>;
 }
 
-use cgmath::Vector2;
-use gfx;
+pub mod circle {
+    use super::buffer_types;
+    use super::formats;
+    use super::instance_renderer::{self, PipelineData, ShaderBytes};
+    use gfx;
 
-pub struct InstanceWriter<'a, R: gfx::Resources, T: 'a + Copy> {
-    num_instances: &'a mut usize,
-    bundle_slice_instances: &'a mut Option<(u32, u32)>,
-    writer: gfx::mapping::Writer<'a, R, T>,
-}
+    gfx_vertex_struct!(Instance {
+        centre: [f32; 2] = "i_Centre",
+        radius: f32 = "i_Radius",
+        colour: [f32; 4] = "i_Colour",
+    });
 
-pub struct InstanceWriterIterMut<'a, T: 'a> {
-    num_instances: &'a mut usize,
-    iter_mut: ::std::slice::IterMut<'a, T>,
-}
+    gfx_pipeline!(pipe {
+        quad_corners: gfx::VertexBuffer<buffer_types::QuadCorners> = (),
+        instances: gfx::InstanceBuffer<Instance> = (),
+        properties: gfx::ConstantBuffer<buffer_types::WindowProperties> = "WindowProperties",
+        camera: gfx::ConstantBuffer<buffer_types::CameraProperties> = "CameraProperties",
+        scissor: gfx::Scissor = (),
+        target: gfx::BlendTarget<formats::Colour> =
+            ("Target", gfx::state::ColorMask::all(), gfx::preset::blend::ALPHA),
+    });
 
-impl<'a, T> Iterator for InstanceWriterIterMut<'a, T> {
-    type Item = &'a mut T;
-    fn next(&mut self) -> Option<Self::Item> {
-        *self.num_instances += 1;
-        self.iter_mut.next()
+    impl<R: gfx::Resources> PipelineData<R> for pipe::Data<R> {
+        type Instance = Instance;
+        type PipeInit = pipe::Init<'static>;
+        fn new_data(
+            corners: gfx::handle::Buffer<R, buffer_types::QuadCorners>,
+            instances: gfx::handle::Buffer<R, Self::Instance>,
+            properties: gfx::handle::Buffer<R, buffer_types::WindowProperties>,
+            camera: gfx::handle::Buffer<R, buffer_types::CameraProperties>,
+            scissor: gfx::Rect,
+            target: gfx::handle::RenderTargetView<R, formats::Colour>,
+        ) -> Self {
+            pipe::Data {
+                quad_corners: corners,
+                instances,
+                properties,
+                camera,
+                scissor,
+                target,
+            }
+        }
+        fn new_pipe() -> Self::PipeInit {
+            pipe::new()
+        }
+        fn instances(&self) -> &gfx::handle::Buffer<R, Self::Instance> {
+            &self.instances
+        }
+        fn instances_mut(&mut self) -> &mut gfx::handle::Buffer<R, Self::Instance> {
+            &mut self.instances
+        }
+        fn scissor_mut(&mut self) -> &mut gfx::Rect {
+            &mut self.scissor
+        }
+        fn target_mut(&mut self) -> &mut gfx::handle::RenderTargetView<R, formats::Colour> {
+            &mut self.target
+        }
+        fn shader_bytes() -> ShaderBytes {
+            ShaderBytes {
+                vertex: include_bytes!("shaders/circle/shader.150.vert"),
+                fragment: include_bytes!("shaders/circle/shader.150.frag"),
+            }
+        }
     }
+
+    pub type Renderer<R> = instance_renderer::Renderer<R, pipe::Data<R>>;
 }
 
-impl<'a, R: gfx::Resources, T: Copy> Drop for InstanceWriter<'a, R, T> {
-    fn drop(&mut self) {
-        *self.bundle_slice_instances = Some((*self.num_instances as u32, 0));
+/// Filled convex polygons, triangulated on the CPU into a vertex buffer which is rebuilt
+/// every frame. Polygons have different numbers of vertices, so they don't suit
+/// instancing.
+pub mod polygon {
+    use super::buffer_types;
+    use super::formats;
+    use super::instance_renderer::ShaderBytes;
+    use gfx;
+
+    /// Starts this big, and doubles whenever a frame needs more.
+    const INITIAL_NUM_VERTICES: usize = 1024;
+
+    gfx_vertex_struct!(Vertex {
+        position: [f32; 2] = "a_Position",
+        colour: [f32; 4] = "a_Colour",
+    });
+
+    gfx_pipeline!(pipe {
+        vertices: gfx::VertexBuffer<Vertex> = (),
+        properties: gfx::ConstantBuffer<buffer_types::WindowProperties> = "WindowProperties",
+        camera: gfx::ConstantBuffer<buffer_types::CameraProperties> = "CameraProperties",
+        scissor: gfx::Scissor = (),
+        target: gfx::BlendTarget<formats::Colour> =
+            ("Target", gfx::state::ColorMask::all(), gfx::preset::blend::ALPHA),
+    });
+
+    fn shader_bytes() -> ShaderBytes {
+        ShaderBytes {
+            vertex: include_bytes!("shaders/polygon/shader.150.vert"),
+            fragment: include_bytes!("shaders/polygon/shader.150.frag"),
+        }
     }
-}
 
-impl<'a, R: gfx::Resources, T: Copy> InstanceWriter<'a, R, T> {
-    pub fn iter_mut(&mut self) -> InstanceWriterIterMut<T> {
-        InstanceWriterIterMut {
-            num_instances: &mut self.num_instances,
-            iter_mut: self.writer.iter_mut(),
+    fn create_vertex_buffer<R, F>(
+        size: usize,
+        factory: &mut F,
+    ) -> gfx::handle::Buffer<R, Vertex>
+    where
+        R: gfx::Resources,
+        F: gfx::Factory<R> + gfx::traits::FactoryExt<R>,
+    {
+        factory
+            .create_buffer(
+                size,
+                gfx::buffer::Role::Vertex,
+                gfx::memory::Usage::Dynamic,
+                gfx::memory::Bind::empty(),
+            )
+            .expect("Failed to create buffer")
+    }
+
+    pub struct Renderer<R: gfx::Resources> {
+        bundle: gfx::Bundle<R, pipe::Data<R>>,
+        vertices: Vec<Vertex>,
+        capacity: usize,
+        high_water_mark: usize,
+    }
+
+    impl<R: gfx::Resources> Renderer<R> {
+        pub fn new<F>(
+            colour_rtv: &gfx::handle::RenderTargetView<R, formats::Colour>,
+            window_properties: &gfx::handle::Buffer<R, buffer_types::WindowProperties>,
+            camera_properties: &gfx::handle::Buffer<R, buffer_types::CameraProperties>,
+            scissor: gfx::Rect,
+            factory: &mut F,
+        ) -> Self
+        where
+            F: gfx::Factory<R> + gfx::traits::FactoryExt<R>,
+        {
+            let shader_bytes = shader_bytes();
+            let pso = factory
+                .create_pipeline_simple(shader_bytes.vertex, shader_bytes.fragment, pipe::new())
+                .expect("Failed to create pipeline");
+            let data = pipe::Data {
+                vertices: create_vertex_buffer(INITIAL_NUM_VERTICES, factory),
+                properties: window_properties.clone(),
+                camera: camera_properties.clone(),
+                scissor,
+                target: colour_rtv.clone(),
+            };
+            let slice = gfx::Slice {
+                start: 0,
+                end: 0,
+                base_vertex: 0,
+                instances: None,
+                buffer: gfx::IndexBuffer::Auto,
+            };
+            let bundle = gfx::pso::bundle::Bundle::new(slice, pso, data);
+
+            Self {
+                bundle,
+                vertices: Vec::new(),
+                capacity: INITIAL_NUM_VERTICES,
+                high_water_mark: 0,
+            }
+        }
+
+        /// The most vertices drawn in a single frame so far.
+        pub fn high_water_mark(&self) -> usize {
+            self.high_water_mark
+        }
+
+        /// The number of vertices the buffer currently has room for.
+        pub fn capacity(&self) -> usize {
+            self.capacity
+        }
+
+        pub fn set_target(
+            &mut self,
+            colour_rtv: &gfx::handle::RenderTargetView<R, formats::Colour>,
+        ) {
+            self.bundle.data.target = colour_rtv.clone();
+        }
+
+        pub fn set_scissor(&mut self, scissor: gfx::Rect) {
+            self.bundle.data.scissor = scissor;
+        }
+
+        /// Clears the triangles from the last frame, and returns the list to add this
+        /// frame's triangles to, three vertices at a time.
+        pub fn begin_frame(&mut self) -> &mut Vec<Vertex> {
+            self.vertices.clear();
+            &mut self.vertices
+        }
+
+        /// Writes this frame's vertices to the vertex buffer, first replacing it with a
+        /// bigger one if they don't fit.
+        pub fn upload<F, C>(&mut self, factory: &mut F, encoder: &mut gfx::Encoder<R, C>)
+        where
+            F: gfx::Factory<R> + gfx::traits::FactoryExt<R>,
+            C: gfx::CommandBuffer<R>,
+        {
+            if self.vertices.len() > self.capacity {
+                let mut capacity = self.capacity;
+                while capacity < self.vertices.len() {
+                    capacity *= 2;
+                }
+                self.bundle.data.vertices = create_vertex_buffer(capacity, factory);
+                self.capacity = capacity;
+            }
+            self.high_water_mark = self.high_water_mark.max(self.vertices.len());
+            if !self.vertices.is_empty() {
+                encoder
+                    .update_buffer(&self.bundle.data.vertices, &self.vertices, 0)
+                    .expect("Failed to update vertices");
+            }
+        }
+
+        /// Draws the triangles made of the vertices in `start..end`.
+        pub fn encode<C>(
+            &mut self,
+            start: usize,
+            end: usize,
+            encoder: &mut gfx::Encoder<R, C>,
+        ) where
+            C: gfx::CommandBuffer<R>,
+        {
+            self.bundle.slice.start = start as u32;
+            self.bundle.slice.end = end as u32;
+            self.bundle.encode(encoder);
         }
     }
 }
 
-pub struct Frame<'a, R: gfx::Resources> {
-    quad: InstanceWriter<'a, R, quad::Instance>,
-    line_segment: InstanceWriter<'a, R, line_segment::Instance>,
+use camera::Camera;
+use vector::{Vector2, vec2};
+use draw::DrawTarget;
+use gfx;
+use viewport::Viewport;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pipeline {
+    Quad,
+    Polygon,
+    Circle,
+    LineSegment,
 }
 
-impl<'a, R: gfx::Resources> Frame<'a, R> {
-    pub fn updater(&mut self) -> FrameUpdater {
-        FrameUpdater {
-            quad: self.quad.iter_mut(),
-            line_segment: self.line_segment.iter_mut(),
-        }
-    }
+/// Consecutive draw calls which use the same pipeline, as a range of that pipeline's
+/// instances (or vertices, for polygons).
+#[derive(Debug, Clone, Copy)]
+struct Batch {
+    pipeline: Pipeline,
+    start: usize,
+    end: usize,
 }
 
+/// Collects a frame's instances. They're sent to the GPU by `Renderer::encode`, which
+/// draws them in the order they were added.
 pub struct FrameUpdater<'a> {
-    quad: InstanceWriterIterMut<'a, quad::Instance>,
-    line_segment: InstanceWriterIterMut<'a, line_segment::Instance>,
+    quad: &'a mut Vec<quad::Instance>,
+    polygon: &'a mut Vec<polygon::Vertex>,
+    circle: &'a mut Vec<circle::Instance>,
+    line_segment: &'a mut Vec<line_segment::Instance>,
+    batches: &'a mut Vec<Batch>,
 }
 
 impl<'a> FrameUpdater<'a> {
-    pub fn axis_aligned_rect(
+    /// Records that `pipeline` now has `len` instances, extending the last batch if it
+    /// was for the same pipeline.
+    fn extend_batch(&mut self, pipeline: Pipeline, len: usize) {
+        if let Some(batch) = self.batches.last_mut() {
+            if batch.pipeline == pipeline {
+                batch.end = len;
+                return;
+            }
+        }
+        let start = self.batches
+            .iter()
+            .rev()
+            .find(|batch| batch.pipeline == pipeline)
+            .map_or(0, |batch| batch.end);
+        self.batches.push(Batch {
+            pipeline,
+            start,
+            end: len,
+        });
+    }
+}
+
+impl<'a> DrawTarget for FrameUpdater<'a> {
+    fn axis_aligned_rect(
         &mut self,
         top_left: Vector2<f32>,
         size: Vector2<f32>,
-        colour: [f32; 3],
+        colour: [f32; 4],
     ) {
-        if let Some(quad) = self.quad.next() {
-            quad.position_of_top_left_in_pixels = top_left.into();
-            quad.dimensions_in_pixels = size.into();
-            quad.colour = colour;
-        }
+        self.quad.push(quad::Instance {
+            position_of_top_left_in_pixels: top_left.into(),
+            dimensions_in_pixels: size.into(),
+            colour,
+        });
+        let len = self.quad.len();
+        self.extend_batch(Pipeline::Quad, len);
     }
-    pub fn line_segment(
+    fn line_segment(
         &mut self,
         start: Vector2<f32>,
         end: Vector2<f32>,
-        colour: [f32; 3],
+        thickness: f32,
+        colour: [f32; 4],
     ) {
-        if let Some(line_segment) = self.line_segment.next() {
-            line_segment.start = start.into();
-            line_segment.end = end.into();
-            line_segment.colour = colour;
+        self.line_segment.push(line_segment::Instance {
+            start: start.into(),
+            end: end.into(),
+            thickness,
+            colour,
+        });
+        let len = self.line_segment.len();
+        self.extend_batch(Pipeline::LineSegment, len);
+    }
+    fn convex_polygon(&mut self, vertices: &[Vector2<f32>], colour: [f32; 4]) {
+        if vertices.len() < 3 {
+            return;
+        }
+        let vertex = |position: Vector2<f32>| polygon::Vertex {
+            position: position.into(),
+            colour,
+        };
+        // a fan from the first vertex covers any convex polygon
+        for pair in vertices[1..].windows(2) {
+            self.polygon.push(vertex(vertices[0]));
+            self.polygon.push(vertex(pair[0]));
+            self.polygon.push(vertex(pair[1]));
         }
+        let len = self.polygon.len();
+        self.extend_batch(Pipeline::Polygon, len);
+    }
+    fn circle(&mut self, centre: Vector2<f32>, radius: f32, colour: [f32; 4]) {
+        self.circle.push(circle::Instance {
+            centre: centre.into(),
+            radius,
+            colour,
+        });
+        let len = self.circle.len();
+        self.extend_batch(Pipeline::Circle, len);
     }
 }
 
+/// The most of each kind of instance drawn in a single frame so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HighWaterMarks {
+    pub quads: usize,
+    pub polygon_vertices: usize,
+    pub circles: usize,
+    pub line_segments: usize,
+}
+
 pub struct Renderer<R: gfx::Resources> {
-    pub quad: quad::Renderer<R>,
-    pub line_segment: line_segment::Renderer<R>,
+    quad: quad::Renderer<R>,
+    polygon: polygon::Renderer<R>,
+    circle: circle::Renderer<R>,
+    line_segment: line_segment::Renderer<R>,
+    window_properties: gfx::handle::Buffer<R, buffer_types::WindowProperties>,
+    camera_properties: gfx::handle::Buffer<R, buffer_types::CameraProperties>,
+    window_size: Vector2<u16>,
+    viewport: Viewport,
+    batches: Vec<Batch>,
 }
 
 impl<R: gfx::Resources> Renderer<R> {
@@ -415,34 +844,146 @@ impl<R: gfx::Resources> Renderer<R> {
         C: gfx::CommandBuffer<R>,
     {
         let window_properties = buffer_alloc::create_window_property_buffer(factory);
+        let window_size = dimensions::rtv_dimensions(&colour_rtv);
+        let viewport = Viewport::default();
         buffer_types::update_window_properties(
             &window_properties,
-            dimensions::rtv_dimensions(&colour_rtv),
+            window_size,
+            &viewport,
+            encoder,
+        );
+        let scissor = dimensions::scissor(&viewport, window_size);
+        let camera_properties = buffer_alloc::create_camera_property_buffer(factory);
+        buffer_types::update_camera_properties(
+            &camera_properties,
+            &Camera::new(vec2(window_size.x as f32, window_size.y as f32)),
             encoder,
         );
         Self {
-            quad: quad::Renderer::new(&colour_rtv, &window_properties, factory),
+            quad: quad::Renderer::new(
+                &colour_rtv,
+                &window_properties,
+                &camera_properties,
+                scissor,
+                factory,
+            ),
+            polygon: polygon::Renderer::new(
+                &colour_rtv,
+                &window_properties,
+                &camera_properties,
+                scissor,
+                factory,
+            ),
+            circle: circle::Renderer::new(
+                &colour_rtv,
+                &window_properties,
+                &camera_properties,
+                scissor,
+                factory,
+            ),
             line_segment: line_segment::Renderer::new(
                 &colour_rtv,
                 &window_properties,
+                &camera_properties,
+                scissor,
                 factory,
             ),
+            window_properties,
+            camera_properties,
+            window_size,
+            viewport,
+            batches: Vec::new(),
+        }
+    }
+    pub fn window_size(&self) -> Vector2<u16> {
+        self.window_size
+    }
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+    pub fn high_water_marks(&self) -> HighWaterMarks {
+        HighWaterMarks {
+            quads: self.quad.high_water_mark(),
+            polygon_vertices: self.polygon.high_water_mark(),
+            circles: self.circle.high_water_mark(),
+            line_segments: self.line_segment.high_water_mark(),
         }
     }
-    pub fn prepare_frame<F>(&mut self, factory: &mut F) -> Frame<R>
+    pub fn set_viewport<C>(&mut self, viewport: Viewport, encoder: &mut gfx::Encoder<R, C>)
     where
-        F: gfx::Factory<R> + gfx::traits::FactoryExt<R>,
+        C: gfx::CommandBuffer<R>,
+    {
+        self.viewport = viewport;
+        buffer_types::update_window_properties(
+            &self.window_properties,
+            self.window_size,
+            &self.viewport,
+            encoder,
+        );
+        self.update_scissor();
+    }
+    fn update_scissor(&mut self) {
+        let scissor = dimensions::scissor(&self.viewport, self.window_size);
+        self.quad.set_scissor(scissor);
+        self.polygon.set_scissor(scissor);
+        self.circle.set_scissor(scissor);
+        self.line_segment.set_scissor(scissor);
+    }
+    pub fn set_camera<C>(&mut self, camera: &Camera, encoder: &mut gfx::Encoder<R, C>)
+    where
+        C: gfx::CommandBuffer<R>,
     {
-        Frame {
-            quad: self.quad.instance_writer(factory),
-            line_segment: self.line_segment.instance_writer(factory),
+        buffer_types::update_camera_properties(&self.camera_properties, camera, encoder);
+    }
+    /// Switches to the render target of a resized window. The viewport is left alone, so
+    /// it will usually need updating too.
+    pub fn resize<C>(
+        &mut self,
+        colour_rtv: gfx::handle::RenderTargetView<R, formats::Colour>,
+        encoder: &mut gfx::Encoder<R, C>,
+    ) where
+        C: gfx::CommandBuffer<R>,
+    {
+        self.quad.set_target(&colour_rtv);
+        self.polygon.set_target(&colour_rtv);
+        self.circle.set_target(&colour_rtv);
+        self.line_segment.set_target(&colour_rtv);
+        self.window_size = dimensions::rtv_dimensions(&colour_rtv);
+        buffer_types::update_window_properties(
+            &self.window_properties,
+            self.window_size,
+            &self.viewport,
+            encoder,
+        );
+        self.update_scissor();
+    }
+    pub fn prepare_frame(&mut self) -> FrameUpdater {
+        self.batches.clear();
+        FrameUpdater {
+            quad: self.quad.begin_frame(),
+            polygon: self.polygon.begin_frame(),
+            circle: self.circle.begin_frame(),
+            line_segment: self.line_segment.begin_frame(),
+            batches: &mut self.batches,
         }
     }
-    pub fn encode<C>(&self, encoder: &mut gfx::Encoder<R, C>)
+    pub fn encode<F, C>(&mut self, factory: &mut F, encoder: &mut gfx::Encoder<R, C>)
     where
+        F: gfx::Factory<R> + gfx::traits::FactoryExt<R>,
         C: gfx::CommandBuffer<R>,
     {
-        self.quad.encode(encoder);
-        self.line_segment.encode(encoder);
+        self.quad.upload(factory);
+        self.polygon.upload(factory, encoder);
+        self.circle.upload(factory);
+        self.line_segment.upload(factory);
+        for batch in self.batches.iter() {
+            let (start, end) = (batch.start, batch.end);
+            match batch.pipeline {
+                Pipeline::Quad => self.quad.encode(start, end, encoder),
+                Pipeline::Polygon => self.polygon.encode(start, end, encoder),
+                Pipeline::Circle => self.circle.encode(start, end, encoder),
+                Pipeline::LineSegment => self.line_segment.encode(start, end, encoder),
+            }
+        }
     }
 }
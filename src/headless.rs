@@ -0,0 +1,266 @@
+//! Runs a level without opening a window, driven by a script of inputs, and dumps the
+//! position of every entity after each tick.
+//!
+//! An input script has one step per line: a number of ticks followed by the keys held
+//! during them, any of `left`, `right`, `up` and `down`. Blank lines and lines starting
+//! with `#` are ignored. For example:
+//!
+//! ```text
+//! # walk right, then up and right, then stand still
+//! 30 right
+//! 10 right up
+//! 20
+//! ```
+
+use game::{GameState, InputModel};
+use level;
+use software_renderer::SoftwareRenderer;
+use serde_json;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub enum ScriptError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl From<io::Error> for ScriptError {
+    fn from(error: io::Error) -> Self {
+        ScriptError::Io(error)
+    }
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &ScriptError::Io(ref error) => write!(f, "{}", error),
+            &ScriptError::Parse { line, ref message } => {
+                write!(f, "line {}: {}", line, message)
+            }
+        }
+    }
+}
+
+fn parse_error<T>(line: usize, message: String) -> Result<T, ScriptError> {
+    Err(ScriptError::Parse { line, message })
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputScript {
+    steps: Vec<(u32, InputModel)>,
+}
+
+impl InputScript {
+    pub fn read<R: BufRead>(reader: R) -> Result<Self, ScriptError> {
+        let mut steps = Vec::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let ticks = match words.next().map(str::parse) {
+                Some(Ok(ticks)) => ticks,
+                _ => return parse_error(line_number, "expected a number of ticks".to_string()),
+            };
+            let mut input_model = InputModel::default();
+            for word in words {
+                match word {
+                    "left" => input_model.set_left(1.),
+                    "right" => input_model.set_right(1.),
+                    "up" => input_model.set_up(1.),
+                    "down" => input_model.set_down(1.),
+                    _ => return parse_error(line_number, format!("unknown key \"{}\"", word)),
+                }
+            }
+            steps.push((ticks, input_model));
+        }
+        Ok(Self { steps })
+    }
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ScriptError> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+    pub fn num_ticks(&self) -> u32 {
+        self.steps.iter().map(|&(ticks, _)| ticks).sum()
+    }
+    /// The input held during `tick`, counting from 0. Nothing is held once the script
+    /// has finished.
+    pub fn input_model(&self, tick: u32) -> InputModel {
+        let mut start = 0;
+        for &(ticks, input_model) in self.steps.iter() {
+            if tick < start + ticks {
+                return input_model;
+            }
+            start += ticks;
+        }
+        InputModel::default()
+    }
+}
+
+impl FromStr for InputScript {
+    type Err = ScriptError;
+    fn from_str(s: &str) -> Result<Self, ScriptError> {
+        Self::read(s.as_bytes())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown format \"{}\", expected csv or json", s)),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct EntityDump {
+    id: u32,
+    position: [f64; 2],
+}
+
+#[derive(Serialize)]
+struct TickDump {
+    tick: u32,
+    entities: Vec<EntityDump>,
+}
+
+fn dump(tick: u32, game_state: &GameState) -> TickDump {
+    TickDump {
+        tick,
+        entities: game_state
+            .positions()
+            .into_iter()
+            .map(|(id, position)| EntityDump {
+                id,
+                position: level::vector_to_pixels(position),
+            })
+            .collect(),
+    }
+}
+
+/// Runs `num_ticks` ticks, writing positions in pixels before the first tick (as tick 0)
+/// and after every tick.
+pub fn run<W: Write>(
+    game_state: &mut GameState,
+    script: &InputScript,
+    num_ticks: u32,
+    format: Format,
+    mut writer: W,
+) -> io::Result<()> {
+    let mut ticks = Vec::new();
+    if format == Format::Csv {
+        writeln!(writer, "tick,id,x,y")?;
+    }
+    for tick in 0..(num_ticks + 1) {
+        if tick > 0 {
+            game_state.update(&script.input_model(tick - 1));
+        }
+        let tick_dump = dump(tick, game_state);
+        match format {
+            Format::Csv => for entity in tick_dump.entities.iter() {
+                writeln!(
+                    writer,
+                    "{},{},{},{}",
+                    tick, entity.id, entity.position[0], entity.position[1]
+                )?;
+            },
+            Format::Json => ticks.push(tick_dump),
+        }
+    }
+    if format == Format::Json {
+        serde_json::to_writer(&mut writer, &ticks)?;
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Draws every entity into a new image of the given size.
+pub fn capture(game_state: &GameState, width: u32, height: u32) -> SoftwareRenderer {
+    let mut renderer = SoftwareRenderer::new(width, height);
+    game_state.draw(&mut renderer);
+    renderer
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use vector::vec2;
+    use serde_json::Value;
+
+    fn demo() -> GameState {
+        let mut game_state = GameState::new(vec2(960., 640.));
+        game_state.init_demo();
+        game_state
+    }
+
+    #[test]
+    fn script() {
+        let script = "# comment\n2 right down\n\n1\n".parse::<InputScript>().unwrap();
+        assert_eq!(script.num_ticks(), 3);
+        let mut right_down = InputModel::default();
+        right_down.set_right(1.);
+        right_down.set_down(1.);
+        assert_eq!(script.input_model(1), right_down);
+        assert_eq!(script.input_model(2), InputModel::default());
+        assert_eq!(script.input_model(10), InputModel::default());
+        match "3 jump".parse::<InputScript>() {
+            Err(ScriptError::Parse { line: 1, .. }) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn csv() {
+        let script = "5 right".parse::<InputScript>().unwrap();
+        let mut output = Vec::new();
+        run(&mut demo(), &script, 5, Format::Csv, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        let num_entities = demo().positions().len();
+        assert_eq!(lines[0], "tick,id,x,y");
+        assert_eq!(lines.len(), 1 + 6 * num_entities);
+        assert_eq!(lines[1], "0,0,200,50");
+    }
+
+    #[test]
+    fn json_matches_simulation() {
+        let script = "5 right".parse::<InputScript>().unwrap();
+        let mut output = Vec::new();
+        run(&mut demo(), &script, 5, Format::Json, &mut output).unwrap();
+        let output: Value = serde_json::from_slice(&output).unwrap();
+        let mut game_state = demo();
+        for _ in 0..5 {
+            game_state.update(&script.input_model(0));
+        }
+        let (id, position) = game_state.positions()[0];
+        let last = &output[5]["entities"][0];
+        assert_eq!(last["id"], id);
+        assert_eq!(last["position"][0], level::vector_to_pixels(position)[0]);
+    }
+
+    #[test]
+    fn capture_demo() {
+        let renderer = capture(&demo(), 960, 640);
+        // inside the player, the floor and the sensor
+        assert_eq!(renderer.pixel(210, 60), [255, 0, 0, 255]);
+        assert_eq!(renderer.pixel(60, 210)[3], 255);
+        assert_ne!(renderer.pixel(60, 210), [0, 0, 0, 255]);
+        assert_ne!(renderer.pixel(610, 70), [0, 0, 0, 255]);
+        assert_eq!(renderer.pixel(5, 5), [0, 0, 0, 255]);
+    }
+}
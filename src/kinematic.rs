@@ -0,0 +1,73 @@
+use vector::Vector2;
+use pixel_num::sub_pixel_i64::{self, SubPixelI64};
+
+#[derive(Debug, Clone)]
+pub struct KinematicPath {
+    waypoints: Vec<Vector2<SubPixelI64>>,
+    speed: SubPixelI64,
+    next_waypoint: usize,
+}
+
+impl KinematicPath {
+    pub fn new(waypoints: Vec<Vector2<SubPixelI64>>, speed: SubPixelI64) -> Self {
+        assert!(!waypoints.is_empty(), "path must have at least one waypoint");
+        Self {
+            waypoints,
+            speed,
+            next_waypoint: 0,
+        }
+    }
+    pub fn waypoints(&self) -> &[Vector2<SubPixelI64>] {
+        &self.waypoints
+    }
+    pub fn speed(&self) -> SubPixelI64 {
+        self.speed
+    }
+    pub fn next_waypoint(&self) -> usize {
+        self.next_waypoint
+    }
+    fn advance(&mut self) {
+        self.next_waypoint = (self.next_waypoint + 1) % self.waypoints.len();
+    }
+    pub fn movement(&mut self, position: Vector2<SubPixelI64>) -> Vector2<SubPixelI64> {
+        let mut to_waypoint = self.waypoints[self.next_waypoint] - position;
+        if sub_pixel_i64::vector_is_zero(to_waypoint) {
+            self.advance();
+            to_waypoint = self.waypoints[self.next_waypoint] - position;
+        }
+        let movement = sub_pixel_i64::vector_clamp_length(to_waypoint, self.speed);
+        if movement == to_waypoint {
+            self.advance();
+        }
+        movement
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use vector::vec2;
+
+    fn v(x: i64, y: i64) -> Vector2<SubPixelI64> {
+        vec2(SubPixelI64::new(x), SubPixelI64::new(y))
+    }
+
+    #[test]
+    fn follows_waypoints() {
+        let mut path = KinematicPath::new(vec![v(10, 0), v(10, 10)], SubPixelI64::new(4));
+        assert_eq!(path.movement(v(0, 0)), v(4, 0));
+        assert_eq!(path.movement(v(8, 0)), v(2, 0));
+        assert_eq!(path.next_waypoint(), 1);
+        assert_eq!(path.movement(v(10, 0)), v(0, 4));
+        assert_eq!(path.movement(v(10, 4)), v(0, 4));
+        assert_eq!(path.movement(v(10, 8)), v(0, 2));
+        assert_eq!(path.next_waypoint(), 0);
+        assert_eq!(path.movement(v(10, 10)), v(0, -4));
+    }
+
+    #[test]
+    fn diagonal_speed() {
+        let mut path = KinematicPath::new(vec![v(30, 40)], SubPixelI64::new(10));
+        assert_eq!(path.movement(v(0, 0)), v(6, 8));
+    }
+}
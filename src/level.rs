@@ -0,0 +1,383 @@
+use vector::{Vector2, vec2};
+use line_segment::LineSegment;
+use axis_aligned_rect::AxisAlignedRect;
+use pixel_num::SubPixelI64;
+use serde_json;
+use shape::Shape;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::str::FromStr;
+use tile_map::TileMap;
+use tiled;
+
+/// A level as stored on disk. Coordinates are in pixels, and are rounded to the nearest
+/// sub-pixel when loaded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Level {
+    pub size: [f64; 2],
+    pub entities: Vec<EntityDesc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera_bounds: Option<CameraBounds>,
+}
+
+/// The region the camera is kept inside. Without it the camera stays inside the level.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraBounds {
+    pub position: [f64; 2],
+    pub size: [f64; 2],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Flag {
+    Player,
+    Pushable,
+    Sensor,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ShapeDesc {
+    AxisAlignedRect { size: [f64; 2] },
+    LineSegment { start: [f64; 2], end: [f64; 2] },
+    /// One string per row of cells, with '#' for solid cells and '.' for empty ones.
+    TileMap { tile_size: [f64; 2], rows: Vec<String> },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PathDesc {
+    pub waypoints: Vec<[f64; 2]>,
+    pub speed: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EntityDesc {
+    pub position: [f64; 2],
+    pub shape: ShapeDesc,
+    pub colour: [f32; 3],
+    /// Opacity from 0 to 1. Defaults to 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alpha: Option<f32>,
+    /// Entities are drawn in increasing order of layer, which defaults to 0.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layer: Option<i32>,
+    /// The thickness of a line segment in pixels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_thickness: Option<f32>,
+    /// Draws only an outline this many pixels thick, instead of filling, for rectangles
+    /// and tile maps.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outline: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub velocity: Option<[f64; 2]>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<Flag>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mass: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathDesc>,
+}
+
+impl EntityDesc {
+    pub fn has_flag(&self, flag: Flag) -> bool {
+        self.flags.contains(&flag)
+    }
+    pub fn rgba(&self) -> [f32; 4] {
+        [
+            self.colour[0],
+            self.colour[1],
+            self.colour[2],
+            self.alpha.unwrap_or(1.),
+        ]
+    }
+}
+
+pub fn vector_from_pixels(v: [f64; 2]) -> Vector2<SubPixelI64> {
+    vec2(
+        SubPixelI64::new_pixels_f64(v[0]),
+        SubPixelI64::new_pixels_f64(v[1]),
+    )
+}
+
+pub fn vector_to_pixels(v: Vector2<SubPixelI64>) -> [f64; 2] {
+    [v.x.to_pixels_f64(), v.y.to_pixels_f64()]
+}
+
+impl ShapeDesc {
+    pub fn from_shape(shape: &Shape<SubPixelI64>) -> Self {
+        match shape {
+            &Shape::AxisAlignedRect(ref rect) => ShapeDesc::AxisAlignedRect {
+                size: vector_to_pixels(rect.dimensions()),
+            },
+            &Shape::LineSegment(ref line_segment) => ShapeDesc::LineSegment {
+                start: vector_to_pixels(line_segment.start),
+                end: vector_to_pixels(line_segment.end),
+            },
+            &Shape::TileMap(ref tile_map) => ShapeDesc::TileMap {
+                tile_size: vector_to_pixels(tile_map.tile_size()),
+                rows: (0..tile_map.height())
+                    .map(|y| {
+                        (0..tile_map.width())
+                            .map(|x| if tile_map.is_solid(x as i64, y as i64) { '#' } else { '.' })
+                            .collect()
+                    })
+                    .collect(),
+            },
+        }
+    }
+    pub fn to_shape(&self) -> Shape<SubPixelI64> {
+        match self {
+            &ShapeDesc::AxisAlignedRect { size } => {
+                Shape::AxisAlignedRect(AxisAlignedRect::new(vector_from_pixels(size)))
+            }
+            &ShapeDesc::LineSegment { start, end } => Shape::LineSegment(LineSegment::new(
+                vector_from_pixels(start),
+                vector_from_pixels(end),
+            )),
+            &ShapeDesc::TileMap {
+                tile_size,
+                ref rows,
+            } => {
+                let width = rows.first().map_or(0, |row| row.len());
+                let solid = rows.iter()
+                    .flat_map(|row| row.chars().map(|c| c == '#'))
+                    .collect();
+                Shape::TileMap(Rc::new(TileMap::new(
+                    vector_from_pixels(tile_size),
+                    width,
+                    rows.len(),
+                    solid,
+                )))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io(io::Error),
+    Parse {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    Invalid(String),
+}
+
+impl From<io::Error> for LevelError {
+    fn from(error: io::Error) -> Self {
+        LevelError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for LevelError {
+    fn from(error: serde_json::Error) -> Self {
+        if error.is_io() {
+            LevelError::Io(error.into())
+        } else {
+            LevelError::Parse {
+                line: error.line(),
+                column: error.column(),
+                message: error.to_string(),
+            }
+        }
+    }
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &LevelError::Io(ref error) => write!(f, "{}", error),
+            &LevelError::Parse { ref message, .. } => write!(f, "{}", message),
+            &LevelError::Invalid(ref message) => write!(f, "invalid level: {}", message),
+        }
+    }
+}
+
+impl Level {
+    pub fn validate(&self) -> Result<(), LevelError> {
+        let num_players = self.entities
+            .iter()
+            .filter(|entity| entity.has_flag(Flag::Player))
+            .count();
+        if num_players != 1 {
+            return Err(LevelError::Invalid(format!(
+                "expected exactly 1 player but found {}",
+                num_players
+            )));
+        }
+        if let Some(camera_bounds) = self.camera_bounds {
+            if camera_bounds.size[0] <= 0. || camera_bounds.size[1] <= 0. {
+                return Err(LevelError::Invalid(
+                    "camera bounds have a non-positive size".to_string(),
+                ));
+            }
+        }
+        for (index, entity) in self.entities.iter().enumerate() {
+            if let Some(ref path) = entity.path {
+                if path.waypoints.is_empty() {
+                    return Err(LevelError::Invalid(format!(
+                        "entity {} has a path with no waypoints",
+                        index
+                    )));
+                }
+            }
+            let static_shape_name = match entity.shape {
+                ShapeDesc::AxisAlignedRect { .. } => None,
+                ShapeDesc::LineSegment { .. } => Some("line segment"),
+                ShapeDesc::TileMap { .. } => Some("tile map"),
+            };
+            if let Some(shape_name) = static_shape_name {
+                if entity.has_flag(Flag::Player) || entity.has_flag(Flag::Pushable)
+                    || entity.velocity.is_some() || entity.path.is_some()
+                {
+                    return Err(LevelError::Invalid(format!(
+                        "entity {} is a {}, which can't move",
+                        index, shape_name
+                    )));
+                }
+            }
+            if let ShapeDesc::TileMap {
+                tile_size,
+                ref rows,
+            } = entity.shape
+            {
+                if tile_size[0] <= 0. || tile_size[1] <= 0. {
+                    return Err(LevelError::Invalid(format!(
+                        "entity {} has a non-positive tile size",
+                        index
+                    )));
+                }
+                let width = rows.first().map_or(0, |row| row.len());
+                if rows.iter().any(|row| {
+                    row.len() != width || row.chars().any(|c| c != '#' && c != '.')
+                }) {
+                    return Err(LevelError::Invalid(format!(
+                        "entity {} has tile map rows of different widths or unknown cells",
+                        index
+                    )));
+                }
+            }
+            if let Some(alpha) = entity.alpha {
+                if !(alpha >= 0. && alpha <= 1.) {
+                    return Err(LevelError::Invalid(format!(
+                        "entity {} has an alpha outside 0 to 1",
+                        index
+                    )));
+                }
+            }
+            if entity.line_thickness.map_or(false, |thickness| !(thickness > 0.))
+                || entity.outline.map_or(false, |thickness| !(thickness > 0.))
+            {
+                return Err(LevelError::Invalid(format!(
+                    "entity {} has a non-positive line thickness",
+                    index
+                )));
+            }
+            if let Some(mass) = entity.mass {
+                if mass <= 0 {
+                    return Err(LevelError::Invalid(format!(
+                        "entity {} has non-positive mass",
+                        index
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+    pub fn read<R: Read>(reader: R) -> Result<Self, LevelError> {
+        let level: Self = serde_json::from_reader(reader)?;
+        level.validate()?;
+        Ok(level)
+    }
+    pub fn write<W: Write>(&self, writer: W) -> Result<(), LevelError> {
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, LevelError> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), LevelError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writeln!(writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Loads a level, importing it from a Tiled map if the path has a ".tmj" extension.
+pub fn load_any<P: AsRef<Path>>(path: P) -> Result<Level, LevelError> {
+    let path = path.as_ref();
+    if path.extension().map_or(false, |extension| extension == "tmj") {
+        tiled::load(path)
+    } else {
+        Level::load(path)
+    }
+}
+
+impl FromStr for Level {
+    type Err = LevelError;
+    fn from_str(s: &str) -> Result<Self, LevelError> {
+        let level: Self = serde_json::from_str(s)?;
+        level.validate()?;
+        Ok(level)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn demo_round_trip() {
+        let level = include_str!("../levels/demo.json").parse::<Level>().unwrap();
+        let mut buffer = Vec::new();
+        level.write(&mut buffer).unwrap();
+        assert_eq!(Level::read(&buffer[..]).unwrap(), level);
+    }
+
+    #[test]
+    fn parse_error_position() {
+        match "{\n  \"size\": [960, 640],\n  \"entities\": [,]\n}".parse::<Level>() {
+            Err(LevelError::Parse { line, column, .. }) => assert_eq!((line, column), (3, 16)),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn requires_player() {
+        match "{ \"size\": [960, 640], \"entities\": [] }".parse::<Level>() {
+            Err(LevelError::Invalid(_)) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn line_segments_cant_move() {
+        let level = "{
+            \"size\": [960, 640],
+            \"entities\": [
+                { \"position\": [0, 0], \"colour\": [1, 1, 1], \"velocity\": [0, 0],
+                  \"shape\": { \"type\": \"line_segment\",
+                               \"start\": [0, 0], \"end\": [8, 8] } },
+                { \"position\": [100, 100], \"colour\": [1, 0, 0],
+                  \"flags\": [\"player\"],
+                  \"shape\": { \"type\": \"axis_aligned_rect\", \"size\": [8, 8] } }
+            ]
+        }";
+        match level.parse::<Level>() {
+            Err(LevelError::Invalid(message)) => assert!(message.contains("can't move")),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,115 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(alloc)]
+#![feature(nonzero)]
+#![cfg_attr(test, feature(test))]
+// Without std, `::std::` paths (including those generated by newtype_derive) resolve to
+// core. Anything which needs allocation is imported from alloc in both configurations.
+#[cfg(not(feature = "std"))]
+extern crate core as std;
+#[macro_use]
+extern crate alloc;
+#[macro_use]
+extern crate custom_derive;
+#[cfg(feature = "std")]
+extern crate fnv;
+#[cfg(feature = "graphics")]
+#[macro_use]
+extern crate gfx;
+#[cfg(feature = "graphics")]
+extern crate gfx_device_gl;
+#[cfg(feature = "graphics")]
+extern crate gfx_window_glutin;
+#[cfg(feature = "graphics")]
+extern crate glutin;
+extern crate libm;
+#[macro_use]
+extern crate newtype_derive;
+extern crate num;
+#[cfg(feature = "std")]
+extern crate serde;
+#[cfg(feature = "std")]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "std")]
+extern crate serde_json;
+#[cfg(test)]
+extern crate test;
+
+// Modules whose items are used through their module name are public. The rest are
+// implementation detail, and their public types are re-exported below.
+pub(crate) mod aabb;
+pub(crate) mod axis_aligned_rect;
+mod best;
+#[cfg(feature = "std")]
+pub(crate) mod camera;
+pub(crate) mod collision;
+#[cfg(feature = "std")]
+pub(crate) mod contact;
+#[cfg(feature = "std")]
+pub mod debug_overlay;
+pub(crate) mod draw;
+#[cfg(feature = "std")]
+pub(crate) mod fixed_timestep;
+#[cfg(feature = "std")]
+pub(crate) mod game;
+#[cfg(feature = "graphics")]
+pub mod glutin_window;
+#[cfg(feature = "graphics")]
+pub(crate) mod graphics;
+#[cfg(feature = "std")]
+pub mod headless;
+#[cfg(feature = "std")]
+pub(crate) mod kinematic;
+#[cfg(feature = "std")]
+pub(crate) mod level;
+pub(crate) mod line_segment;
+pub(crate) mod loose_quad_tree;
+pub(crate) mod physics_num;
+pub mod pixel_num;
+#[cfg(feature = "std")]
+pub(crate) mod replay;
+pub(crate) mod shape;
+#[cfg(feature = "std")]
+pub(crate) mod software_renderer;
+#[cfg(feature = "std")]
+pub mod svg;
+pub(crate) mod tile_map;
+#[cfg(feature = "std")]
+pub mod tiled;
+pub(crate) mod vector;
+#[cfg(feature = "std")]
+pub(crate) mod viewport;
+
+pub use aabb::{Aabb, AabbSplitFour};
+pub use axis_aligned_rect::AxisAlignedRect;
+#[cfg(feature = "std")]
+pub use camera::Camera;
+pub use collision::{Collision, NoCollision};
+#[cfg(feature = "std")]
+pub use contact::{CollisionEvent, ContactPhase, SensorEvent, SensorPhase};
+pub use draw::{DEFAULT_LINE_THICKNESS, DrawTarget};
+#[cfg(feature = "std")]
+pub use fixed_timestep::FixedTimestep;
+#[cfg(feature = "std")]
+pub use game::{CollisionDebug, EntityId, GameState, InputModel, MovementDebug, RenderUpdate,
+               Snapshot};
+#[cfg(feature = "graphics")]
+pub use graphics::{FrameUpdater, HighWaterMarks, Renderer};
+#[cfg(feature = "std")]
+pub use kinematic::KinematicPath;
+#[cfg(feature = "std")]
+pub use level::{load_any, CameraBounds, EntityDesc, Flag, Level, LevelError, PathDesc,
+                ShapeDesc};
+pub use line_segment::LineSegment;
+pub use loose_quad_tree::LooseQuadTree;
+pub use physics_num::PhysicsNum;
+pub use pixel_num::SubPixelI64;
+#[cfg(feature = "std")]
+pub use replay::{Divergence, Playback, Replay, ReplayError, ReplayTick};
+pub use shape::{Collide, CollisionInfo, Shape};
+#[cfg(feature = "std")]
+pub use software_renderer::SoftwareRenderer;
+pub use tile_map::{TileMap, TileMapRegion};
+pub use vector::{Vector2, vec2};
+#[cfg(feature = "std")]
+pub use viewport::{Scaling, Viewport};
@@ -1,6 +1,7 @@
 use aabb::Aabb;
-use physics_num::PhysicsNum;
-use cgmath::{Vector2, vec2};
+use physics_num::{self, PhysicsNum};
+use vector::{Vector2, vec2};
+use num::Zero;
 use shape::Collide;
 
 #[derive(Debug, Clone, Copy)]
@@ -22,6 +23,34 @@ impl<N: PhysicsNum> LineSegment<N> {
     pub fn vector(&self) -> Vector2<N> {
         self.end - self.start
     }
+    pub fn is_intersecting_aabb(&self, aabb: &Aabb<N>) -> bool {
+        if !self.aabb(vec2(Zero::zero(), Zero::zero())).is_intersecting(aabb) {
+            return false;
+        }
+        let vector = self.vector();
+        let mut any_left = false;
+        let mut any_right = false;
+        for &corner in aabb.corners().iter() {
+            let to_corner = corner - self.start;
+            let cross = vector.x * to_corner.y - vector.y * to_corner.x;
+            any_left |= cross <= Zero::zero();
+            any_right |= cross >= Zero::zero();
+        }
+        any_left && any_right
+    }
+    pub fn closest_point(&self, point: Vector2<N>) -> Vector2<N> {
+        let vector = self.vector();
+        let len2 = physics_num::magnitude2(vector);
+        if len2.is_zero() {
+            return self.start;
+        }
+        let dot = PhysicsNum::clamp(
+            physics_num::dot(point - self.start, vector),
+            Zero::zero(),
+            len2,
+        );
+        self.start + vector * dot / len2
+    }
 }
 
 impl<N: PhysicsNum> Collide<N> for LineSegment<N> {
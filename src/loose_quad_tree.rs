@@ -1,5 +1,6 @@
 use aabb::*;
-use cgmath::{Vector2, vec2};
+use alloc::vec::Vec;
+use vector::{Vector2, vec2};
 use std::num::NonZeroUsize;
 use physics_num::PhysicsNum;
 use num::Zero;
@@ -52,9 +53,14 @@ impl<T, N: PhysicsNum> LooseQuadTree<T, N> {
         }
     }
 
+    pub fn size(&self) -> Vector2<N> {
+        self.size
+    }
+
     pub fn clear(&mut self) {
         self.seq += 1;
         self.nodes[0].reuse(self.seq);
+        self.next_free = 1;
     }
 
     pub fn insert(&mut self, aabb: Aabb<N>, t: T) {
@@ -105,6 +111,50 @@ impl<T, N: PhysicsNum> LooseQuadTree<T, N> {
         }
     }
 
+    pub fn remove<F: FnMut(&T) -> bool>(&mut self, aabb: &Aabb<N>, mut f: F) {
+        let mut centre = aabb.centre();
+        let mut index = 0;
+        let mut max_size = self.size / PhysicsNum::two();
+        loop {
+            let child_offset = {
+                let node = match self.nodes.get_mut(index) {
+                    Some(node) => node,
+                    None => return,
+                };
+                if node.seq != self.seq {
+                    return;
+                }
+                let size = aabb.size();
+                if size.x > max_size.x || size.y > max_size.y {
+                    node.items
+                        .retain(|&(ref item_aabb, ref t)| !(item_aabb == aabb && f(t)));
+                    return;
+                }
+                match node.child_offset {
+                    Some(child_offset) => child_offset.get() as usize,
+                    None => return,
+                }
+            };
+            if centre.x < max_size.x {
+                if centre.y < max_size.y {
+                    index = child_offset + Self::TOP_LEFT;
+                } else {
+                    index = child_offset + Self::BOTTOM_LEFT;
+                    centre.y = centre.y - max_size.y;
+                }
+            } else {
+                if centre.y < max_size.y {
+                    index = child_offset + Self::TOP_RIGHT;
+                    centre.x = centre.x - max_size.x;
+                } else {
+                    index = child_offset + Self::BOTTOM_RIGHT;
+                    centre = centre - max_size;
+                }
+            }
+            max_size = max_size / PhysicsNum::two();
+        }
+    }
+
     fn for_each_intersection_rec<F: FnMut(&Aabb<N>, &T)>(
         nodes: &[Node<T, N>],
         current_index: usize,
@@ -175,6 +225,46 @@ impl<T, N: PhysicsNum> LooseQuadTree<T, N> {
         }
     }
 
+    fn for_each_node_rec<F: FnMut(&Aabb<N>, usize)>(
+        nodes: &[Node<T, N>],
+        seq: u64,
+        current_index: usize,
+        current_node_aabb: &Aabb<N>,
+        f: &mut F,
+    ) {
+        if let Some(node) = nodes.get(current_index) {
+            if node.seq != seq {
+                return;
+            }
+            f(current_node_aabb, node.items.len());
+            if let Some(child_offset) = node.child_offset {
+                let child_offset = child_offset.get() as usize;
+                let AabbSplitFour {
+                    top_left,
+                    top_right,
+                    bottom_left,
+                    bottom_right,
+                } = current_node_aabb.split_four();
+                let children = [
+                    (Self::TOP_LEFT, top_left),
+                    (Self::TOP_RIGHT, top_right),
+                    (Self::BOTTOM_LEFT, bottom_left),
+                    (Self::BOTTOM_RIGHT, bottom_right),
+                ];
+                for &(offset, ref child_aabb) in children.iter() {
+                    Self::for_each_node_rec(nodes, seq, child_offset + offset, child_aabb, f);
+                }
+            }
+        }
+    }
+
+    /// Calls `f` with the bounds and number of items of every node in use. Items in a node
+    /// may extend past its bounds, up to `double_about_centre` of them.
+    pub fn for_each_node<F: FnMut(&Aabb<N>, usize)>(&self, mut f: F) {
+        let root_aabb = Aabb::new(vec2(Zero::zero(), Zero::zero()), self.size);
+        Self::for_each_node_rec(&self.nodes, self.seq, 0, &root_aabb, &mut f);
+    }
+
     pub fn for_each_intersection<F: FnMut(&Aabb<N>, &T)>(
         &self,
         aabb: &Aabb<N>,
@@ -1,62 +1,119 @@
-#![allow(dead_code)]
-#![feature(nonzero)]
-extern crate best;
-extern crate cgmath;
-#[macro_use]
-extern crate custom_derive;
-extern crate fnv;
-#[macro_use]
 extern crate gfx;
-extern crate gfx_device_gl;
-extern crate gfx_window_glutin;
 extern crate glutin;
-#[macro_use]
-extern crate newtype_derive;
-extern crate num;
-
-mod aabb;
-mod axis_aligned_rect;
-mod collision;
-mod game;
-mod glutin_window;
-mod graphics;
-mod line_segment;
-mod loose_quad_tree;
-mod physics_num;
-mod pixel_num;
-mod shape;
-
-use shape::Shape;
-use cgmath::vec2;
-use game::{GameState, InputModel};
-use glutin_window::GlutinWindow;
+extern crate integer_physics;
+
+mod file_watcher;
+
+use file_watcher::FileWatcher;
 use gfx::Device;
 use glutin::GlContext;
-use graphics::Renderer;
-use pixel_num::sub_pixel_i64::vector_to_f32_pixel;
+use integer_physics::debug_overlay;
+use integer_physics::glutin_window::{self, GlutinWindow};
+use integer_physics::pixel_num::sub_pixel_i64::vector_to_f32_pixel;
+use integer_physics::{load_any, vec2, Camera, FixedTimestep, GameState, InputModel, Renderer,
+                      Replay, Scaling, Vector2, Viewport};
+use std::env;
+use std::time::{Duration, Instant};
+
+const TICKS_PER_SECOND: u32 = 60;
+const MAX_TICKS_PER_FRAME: u32 = 8;
+const DEFAULT_LEVEL_PATH: &str = "levels/demo.json";
+const LEVEL_POLL_INTERVAL_MS: u64 = 250;
+const CAMERA_PAN_PIXELS: f32 = 64.;
+const CAMERA_ZOOM_STEP: f32 = 1.25;
+
+#[derive(Default)]
+struct Args {
+    level_path: Option<String>,
+    record_path: Option<String>,
+    replay_path: Option<String>,
+    keep_player_position: bool,
+    stats: bool,
+    scaling: Scaling,
+}
+
+fn parse_args() -> Args {
+    let mut args = Args::default();
+    let mut env_args = env::args().skip(1);
+    while let Some(arg) = env_args.next() {
+        if arg == "--record" {
+            args.record_path = Some(env_args.next().expect("--record requires a path"));
+        } else if arg == "--replay" {
+            args.replay_path = Some(env_args.next().expect("--replay requires a path"));
+        } else if arg == "--scaling" {
+            let name = env_args.next().expect("--scaling requires a mode");
+            args.scaling = name.parse().unwrap_or_else(|e: String| panic!("{}", e));
+        } else if arg == "--keep-player-position" {
+            args.keep_player_position = true;
+        } else if arg == "--stats" {
+            args.stats = true;
+        } else if arg.starts_with("--") || args.level_path.is_some() {
+            panic!("Unexpected argument: {}", arg);
+        } else {
+            args.level_path = Some(arg);
+        }
+    }
+    args
+}
 
 enum ExternalEvent {
     Quit,
     Reset,
+    ToggleDebugOverlay,
+    Resize(u32, u32),
+    PanCamera(Vector2<f32>),
+    ZoomCamera(f32),
+    ToggleCameraFollow,
 }
 
 fn process_input(
     events_loop: &mut glutin::EventsLoop,
     input_model: &mut InputModel,
-) -> Option<ExternalEvent> {
-    let mut external_event = None;
+) -> Vec<ExternalEvent> {
+    let mut external_events = Vec::new();
 
     events_loop.poll_events(|event| match event {
         glutin::Event::WindowEvent { event, .. } => match event {
             glutin::WindowEvent::CloseRequested => {
-                external_event = Some(ExternalEvent::Quit);
+                external_events.push(ExternalEvent::Quit);
+            }
+            glutin::WindowEvent::Resized(width, height) => {
+                external_events.push(ExternalEvent::Resize(width, height));
+            }
+            glutin::WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    glutin::MouseScrollDelta::LineDelta(_, y) => y,
+                    glutin::MouseScrollDelta::PixelDelta(_, y) => y / 16.,
+                };
+                external_events.push(ExternalEvent::ZoomCamera(CAMERA_ZOOM_STEP.powf(lines)));
             }
             glutin::WindowEvent::KeyboardInput { input, .. } => {
                 if let Some(virtual_keycode) = input.virtual_keycode {
                     match input.state {
                         glutin::ElementState::Pressed => match virtual_keycode {
                             glutin::VirtualKeyCode::Return => {
-                                external_event = Some(ExternalEvent::Reset)
+                                external_events.push(ExternalEvent::Reset)
+                            }
+                            glutin::VirtualKeyCode::F1 => {
+                                external_events.push(ExternalEvent::ToggleDebugOverlay)
+                            }
+                            glutin::VirtualKeyCode::F => {
+                                external_events.push(ExternalEvent::ToggleCameraFollow)
+                            }
+                            glutin::VirtualKeyCode::W => external_events
+                                .push(ExternalEvent::PanCamera(vec2(0., -CAMERA_PAN_PIXELS))),
+                            glutin::VirtualKeyCode::A => external_events
+                                .push(ExternalEvent::PanCamera(vec2(-CAMERA_PAN_PIXELS, 0.))),
+                            glutin::VirtualKeyCode::S => external_events
+                                .push(ExternalEvent::PanCamera(vec2(0., CAMERA_PAN_PIXELS))),
+                            glutin::VirtualKeyCode::D => external_events
+                                .push(ExternalEvent::PanCamera(vec2(CAMERA_PAN_PIXELS, 0.))),
+                            glutin::VirtualKeyCode::Equals | glutin::VirtualKeyCode::Add => {
+                                external_events.push(ExternalEvent::ZoomCamera(CAMERA_ZOOM_STEP))
+                            }
+                            glutin::VirtualKeyCode::Minus | glutin::VirtualKeyCode::Subtract => {
+                                external_events
+                                    .push(ExternalEvent::ZoomCamera(1. / CAMERA_ZOOM_STEP))
                             }
                             glutin::VirtualKeyCode::Left => input_model.set_left(1.),
                             glutin::VirtualKeyCode::Right => input_model.set_right(1.),
@@ -79,60 +136,206 @@ fn process_input(
         _ => (),
     });
 
-    external_event
+    external_events
+}
+
+/// The centre of the player as it's drawn, `interpolation` of the way through a tick.
+fn player_centre(game_state: &GameState, interpolation: f32) -> Option<Vector2<f32>> {
+    game_state
+        .player_id()
+        .and_then(|id| game_state.interpolated_aabb(id, interpolation))
+        .map(|aabb| vector_to_f32_pixel(aabb.centre()))
+}
+
+/// Moves the camera straight to the player, inside the bounds of the current level.
+fn reset_camera(camera: &mut Camera, game_state: &GameState) {
+    let bounds = game_state.camera_bounds();
+    camera.set_bounds(
+        vector_to_f32_pixel(bounds.top_left()),
+        vector_to_f32_pixel(bounds.size()),
+    );
+    if let Some(player_centre) = player_centre(game_state, 1.) {
+        camera.look_at(player_centre);
+    }
+}
+
+fn save_recording(recording: &Replay, path: &str) {
+    recording
+        .save(path)
+        .unwrap_or_else(|e| panic!("Failed to save replay {}: {}", path, e));
+}
+
+/// A replay starts from a single level state, so a reset or reload can't be recorded.
+/// Recording stops there, keeping the ticks recorded so far.
+fn stop_recording(recording: &mut Option<Replay>, path: Option<&String>, reason: &str) {
+    if let (Some(recording), Some(path)) = (recording.take(), path) {
+        save_recording(&recording, path);
+        eprintln!(
+            "Stopped recording on {}; saved the ticks so far to {}",
+            reason, path
+        );
+    }
 }
 
 fn main() {
+    let args = parse_args();
+    let replay = args.replay_path.as_ref().map(|path| {
+        Replay::load(path).unwrap_or_else(|e| panic!("Failed to load replay {}: {}", path, e))
+    });
+    let level_path = args.level_path.unwrap_or_else(|| DEFAULT_LEVEL_PATH.to_string());
+    // Replays play back the level they embed, rather than whatever is at the level path.
+    let level = match replay.as_ref() {
+        Some(replay) => replay.level.clone(),
+        None => load_any(&level_path)
+            .unwrap_or_else(|e| panic!("Failed to load level {}: {}", level_path, e)),
+    };
+
     let width = 960;
     let height = 640;
     let GlutinWindow {
         window,
         mut device,
         mut factory,
-        render_target_view,
+        mut render_target_view,
+        mut depth_stencil_view,
         mut events_loop,
         mut encoder,
-        ..
     } = GlutinWindow::new(width, height);
 
     let mut renderer =
         Renderer::new(render_target_view.clone(), &mut factory, &mut encoder);
+    // How much of the world is shown at a zoom of 1, unless the scaling shows more.
+    let view_size = vec2(width as f32, height as f32);
+    renderer.set_viewport(
+        Viewport::new(args.scaling, view_size, view_size),
+        &mut encoder,
+    );
+    let mut camera = Camera::new(view_size);
+    let mut camera_follow = true;
 
     let mut game_state = GameState::new(vec2(width as f32, height as f32));
-    game_state.init_demo();
+    game_state.load_level(&level).expect("Invalid level");
+    let mut initial_state = game_state.snapshot();
+    reset_camera(&mut camera, &game_state);
+    let mut level_watcher = FileWatcher::new(
+        level_path.clone(),
+        Duration::from_millis(LEVEL_POLL_INTERVAL_MS),
+    );
 
     let mut input_model = InputModel::default();
+    let mut timestep = FixedTimestep::new(TICKS_PER_SECOND, MAX_TICKS_PER_FRAME);
+    let mut last_frame = Instant::now();
+    let mut show_debug_overlay = false;
+
+    let mut playback = replay.as_ref().map(|replay| replay.playback());
+    if let Some(ref playback) = playback {
+        if let Err(divergence) = playback.check_initial_state(&game_state) {
+            eprintln!("{}", divergence);
+        }
+    }
+    let mut recording = args.record_path
+        .as_ref()
+        .map(|_| Replay::new(level, &game_state));
 
-    loop {
+    'main: loop {
+        for external_event in process_input(&mut events_loop, &mut input_model) {
+            match external_event {
+                ExternalEvent::Quit => break 'main,
+                ExternalEvent::Reset => {
+                    game_state.restore_snapshot(&initial_state);
+                    playback = replay.as_ref().map(|replay| replay.playback());
+                    stop_recording(&mut recording, args.record_path.as_ref(), "reset");
+                    reset_camera(&mut camera, &game_state);
+                }
+                ExternalEvent::ToggleDebugOverlay => {
+                    show_debug_overlay = !show_debug_overlay;
+                    game_state.set_movement_debug(show_debug_overlay);
+                }
+                ExternalEvent::Resize(width, height) => {
+                    glutin_window::resize(
+                        &window,
+                        width,
+                        height,
+                        &mut render_target_view,
+                        &mut depth_stencil_view,
+                    );
+                    renderer.resize(render_target_view.clone(), &mut encoder);
+                    let window_size = vec2(width as f32, height as f32);
+                    renderer.set_viewport(
+                        Viewport::new(args.scaling, view_size, window_size),
+                        &mut encoder,
+                    );
+                    camera.set_view_size(args.scaling.visible_size(view_size, window_size));
+                }
+                ExternalEvent::PanCamera(offset) => {
+                    camera_follow = false;
+                    camera.pan(offset / camera.zoom());
+                }
+                ExternalEvent::ZoomCamera(factor) => camera.zoom_by(factor),
+                ExternalEvent::ToggleCameraFollow => camera_follow = !camera_follow,
+            }
+        }
         encoder.clear(&render_target_view, [0.0, 0.0, 0.0, 1.0]);
-        match process_input(&mut events_loop, &mut input_model) {
-            Some(ExternalEvent::Quit) => break,
-            Some(ExternalEvent::Reset) => (),
-            None => (),
+        let now = Instant::now();
+        // Reloading would make a replay diverge, so the level is only watched when live.
+        if playback.is_none() && level_watcher.poll(now) {
+            match load_any(&level_path) {
+                Ok(reloaded) => {
+                    stop_recording(&mut recording, args.record_path.as_ref(), "reload");
+                    initial_state = game_state
+                        .reload_level(&reloaded, args.keep_player_position)
+                        .expect("Invalid level");
+                    reset_camera(&mut camera, &game_state);
+                }
+                Err(e) => eprintln!("Failed to reload level {}: {}", level_path, e),
+            }
         }
-        game_state.update(&input_model);
-        {
-            let mut frame = renderer.prepare_frame(&mut factory);
-            let mut updater = frame.updater();
-
-            for update in game_state.render_updates() {
-                match update.shape {
-                    &Shape::AxisAlignedRect(ref rect) => updater.axis_aligned_rect(
-                        vector_to_f32_pixel(update.position),
-                        vector_to_f32_pixel(rect.dimensions()),
-                        update.colour,
-                    ),
-                    &Shape::LineSegment(ref line_segment) => updater.line_segment(
-                        vector_to_f32_pixel(line_segment.start + update.position),
-                        vector_to_f32_pixel(line_segment.end + update.position),
-                        update.colour,
-                    ),
+        let frame_ticks = timestep.ticks_in(now - last_frame);
+        for _ in 0..timestep.ticks_for_frame(now - last_frame) {
+            let replayed = match playback.as_mut().and_then(|p| p.step(&mut game_state)) {
+                Some(Ok(())) => true,
+                Some(Err(divergence)) => {
+                    eprintln!("{}", divergence);
+                    true
                 }
+                None => false,
+            };
+            if !replayed {
+                game_state.update(&input_model);
+                if let Some(ref mut recording) = recording {
+                    recording.record(&input_model, &game_state);
+                }
+            }
+        }
+        last_frame = now;
+        if camera_follow {
+            let interpolation = timestep.interpolation();
+            if let Some(player_centre) = player_centre(&game_state, interpolation) {
+                camera.follow(player_centre, frame_ticks);
             }
         }
-        renderer.encode(&mut encoder);
+        renderer.set_camera(&camera, &mut encoder);
+        {
+            let mut updater = renderer.prepare_frame();
+            game_state.draw_interpolated(timestep.interpolation(), &mut updater);
+            if show_debug_overlay {
+                debug_overlay::draw(&game_state, &mut updater);
+            }
+        }
+        renderer.encode(&mut factory, &mut encoder);
         encoder.flush(&mut device);
         window.swap_buffers().expect("Failed to swap buffers");
         device.cleanup();
     }
+
+    if args.stats {
+        let marks = renderer.high_water_marks();
+        eprintln!(
+            "Most instances in a frame: {} quads, {} polygon vertices, {} circles, {} line segments",
+            marks.quads, marks.polygon_vertices, marks.circles, marks.line_segments
+        );
+    }
+    if let (Some(recording), Some(path)) = (recording, args.record_path) {
+        save_recording(&recording, &path);
+    }
 }
@@ -1,7 +1,22 @@
-use cgmath::{BaseNum, Vector2};
-use num::{One, Signed, Zero};
+use num::{Num, NumCast, One, Signed, Zero};
+use std::fmt;
+use std::ops::{AddAssign, DivAssign, MulAssign, Neg, RemAssign, SubAssign};
+use vector::Vector2;
 
-pub trait PhysicsNum: BaseNum + ::std::ops::Neg<Output = Self> + Signed + Ord {
+pub trait PhysicsNum:
+    Copy
+    + fmt::Debug
+    + Num
+    + NumCast
+    + AddAssign
+    + SubAssign
+    + MulAssign
+    + DivAssign
+    + RemAssign
+    + Neg<Output = Self>
+    + Signed
+    + Ord
+{
     fn two() -> Self {
         two()
     }
@@ -34,3 +49,16 @@ fn clamp<N: PhysicsNum>(v: N, min: N, max: N) -> N {
 fn clamp_zero_one<N: PhysicsNum>(v: N) -> N {
     clamp(v, Zero::zero(), One::one())
 }
+
+pub fn sqrt<N: PhysicsNum>(v: N) -> N {
+    if v <= Zero::zero() {
+        return Zero::zero();
+    }
+    let mut x = v;
+    let mut y = (x + One::one()) / two();
+    while y < x {
+        x = y;
+        y = (x + v / x) / two();
+    }
+    x
+}
@@ -79,6 +79,9 @@ macro_rules! make_i64_wrapper {
             pub fn new(value: i64) -> Self {
                 $name(value)
             }
+            pub fn get(self) -> i64 {
+                self.0
+            }
         }
     };
 }
@@ -86,17 +89,23 @@ macro_rules! make_i64_wrapper {
 pub mod sub_pixel_i64 {
 
     use num::Zero;
-    use physics_num::PhysicsNum;
+    use physics_num::{self, PhysicsNum};
     use super::*;
     use self::pixel_i64::PixelI64;
-    use cgmath::{Vector2, vec2};
+    use vector::{Vector2, vec2};
     make_i64_wrapper!(SubPixelI64);
     pub const SUB_PIXELS_PER_PIXEL: i64 = 256;
-    const SUB_PIXELS_PER_PIXEL_SQUARED: i64 = 65536;
     impl SubPixelI64 {
         pub fn new_pixels_f32(pixels: f32) -> Self {
             SubPixelI64(pixels as i64 * SUB_PIXELS_PER_PIXEL)
         }
+        #[cfg(feature = "std")]
+        pub fn new_pixels_f64(pixels: f64) -> Self {
+            SubPixelI64((pixels * SUB_PIXELS_PER_PIXEL as f64).round() as i64)
+        }
+        pub fn to_pixels_f64(self) -> f64 {
+            self.0 as f64 / SUB_PIXELS_PER_PIXEL as f64
+        }
         pub fn approx_pixel(self) -> PixelI64 {
             PixelI64::new(self.0 / SUB_PIXELS_PER_PIXEL)
         }
@@ -107,14 +116,16 @@ pub mod sub_pixel_i64 {
     pub fn normalize_vector_if_longer_than_one(
         v: Vector2<SubPixelI64>,
     ) -> Vector2<SubPixelI64> {
-        let x2 = v.x.0 * v.x.0;
-        let y2 = v.y.0 * v.y.0;
-        let mag2 = x2 + y2;
-        if mag2 > SUB_PIXELS_PER_PIXEL_SQUARED {
-            let mag = (mag2 as f32).sqrt();
-            let x = (v.x.0 * SUB_PIXELS_PER_PIXEL) as f32 / mag;
-            let y = (v.y.0 * SUB_PIXELS_PER_PIXEL) as f32 / mag;
-            vec2(SubPixelI64(x as i64), SubPixelI64(y as i64))
+        vector_clamp_length(v, SubPixelI64(SUB_PIXELS_PER_PIXEL))
+    }
+    pub fn vector_clamp_length(
+        v: Vector2<SubPixelI64>,
+        max_length: SubPixelI64,
+    ) -> Vector2<SubPixelI64> {
+        let mag2 = physics_num::magnitude2(v);
+        if mag2 > max_length * max_length {
+            let mag = physics_num::sqrt(mag2);
+            vec2(v.x * max_length / mag, v.y * max_length / mag)
         } else {
             v
         }
@@ -125,6 +136,12 @@ pub mod sub_pixel_i64 {
             v.y.0 as f32 / SUB_PIXELS_PER_PIXEL as f32,
         )
     }
+    pub fn vector_from_f32_pixel(v: Vector2<f32>) -> Vector2<SubPixelI64> {
+        vec2(
+            SubPixelI64::new_pixels_f32(v.x),
+            SubPixelI64::new_pixels_f32(v.y),
+        )
+    }
     pub fn vector_to_f32_sub_pixel(v: Vector2<SubPixelI64>) -> Vector2<f32> {
         vec2(v.x.0 as f32, v.y.0 as f32)
     }
@@ -0,0 +1,346 @@
+use game::{GameState, InputModel};
+use level::{Level, LevelError};
+use vector::vec2;
+use serde_json;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+const HEADER: &str = "integer-physics-replay 2";
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+    Level { line: usize, error: LevelError },
+    /// The embedded level doesn't produce the recorded initial state.
+    InitialState(Divergence),
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(error: io::Error) -> Self {
+        ReplayError::Io(error)
+    }
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &ReplayError::Io(ref error) => write!(f, "{}", error),
+            &ReplayError::Parse { line, ref message } => {
+                write!(f, "line {}: {}", line, message)
+            }
+            &ReplayError::Level { line, ref error } => {
+                write!(f, "line {}: invalid level: {}", line, error)
+            }
+            &ReplayError::InitialState(ref divergence) => write!(f, "{}", divergence),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayTick {
+    pub input_model: InputModel,
+    pub checksum: u64,
+}
+
+/// Everything needed to reproduce a run: the level it started from, a checksum of the
+/// state before the first tick, and the input and resulting checksum of every tick. The
+/// level is embedded so a replay still plays back after its level file changes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub level: Level,
+    pub initial_checksum: u64,
+    pub ticks: Vec<ReplayTick>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Divergence {
+    pub tick: usize,
+    pub expected: u64,
+    pub actual: u64,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.tick == 0 {
+            write!(f, "initial state ")?;
+        } else {
+            write!(f, "tick {} ", self.tick)?;
+        }
+        write!(
+            f,
+            "diverged: expected checksum {:016x}, got {:016x}",
+            self.expected, self.actual
+        )
+    }
+}
+
+fn parse_error<T>(line: usize, message: &str) -> Result<T, ReplayError> {
+    Err(ReplayError::Parse {
+        line,
+        message: message.to_string(),
+    })
+}
+
+fn parse_checksum(line: usize, s: &str) -> Result<u64, ReplayError> {
+    u64::from_str_radix(s, 16).or_else(|_| parse_error(line, "invalid checksum"))
+}
+
+impl Replay {
+    pub fn new(level: Level, initial_state: &GameState) -> Self {
+        Self {
+            level,
+            initial_checksum: initial_state.checksum(),
+            ticks: Vec::new(),
+        }
+    }
+    pub fn record(&mut self, input_model: &InputModel, state_after_tick: &GameState) {
+        self.ticks.push(ReplayTick {
+            input_model: *input_model,
+            checksum: state_after_tick.checksum(),
+        });
+    }
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "{}", HEADER)?;
+        let level = serde_json::to_string(&self.level)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        writeln!(writer, "level {}", level)?;
+        writeln!(writer, "initial {:016x}", self.initial_checksum)?;
+        for tick in self.ticks.iter() {
+            let sub_pixels = tick.input_model.to_sub_pixels();
+            writeln!(
+                writer,
+                "tick {} {} {} {} {:016x}",
+                sub_pixels[0], sub_pixels[1], sub_pixels[2], sub_pixels[3], tick.checksum
+            )?;
+        }
+        Ok(())
+    }
+    pub fn read<R: BufRead>(reader: R) -> Result<Self, ReplayError> {
+        let mut level = None;
+        let mut level_line = 0;
+        let mut initial_checksum = None;
+        let mut ticks = Vec::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line?;
+            let line = line.trim();
+            if index == 0 {
+                if line != HEADER {
+                    return parse_error(line_number, "not a replay file");
+                }
+                continue;
+            }
+            if line.is_empty() {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("level") => match line["level".len()..].trim().parse::<Level>() {
+                    Ok(parsed) => {
+                        level = Some(parsed);
+                        level_line = line_number;
+                    }
+                    Err(error) => {
+                        return Err(ReplayError::Level {
+                            line: line_number,
+                            error,
+                        })
+                    }
+                },
+                Some("initial") => match words.next() {
+                    Some(checksum) => {
+                        initial_checksum = Some(parse_checksum(line_number, checksum)?)
+                    }
+                    None => return parse_error(line_number, "missing checksum"),
+                },
+                Some("tick") => {
+                    let words = words.collect::<Vec<_>>();
+                    if words.len() != 5 {
+                        return parse_error(line_number, "expected 4 inputs and a checksum");
+                    }
+                    let mut sub_pixels = [0; 4];
+                    for (value, word) in sub_pixels.iter_mut().zip(words.iter()) {
+                        *value = match word.parse() {
+                            Ok(value) => value,
+                            Err(_) => return parse_error(line_number, "invalid input"),
+                        };
+                    }
+                    ticks.push(ReplayTick {
+                        input_model: InputModel::from_sub_pixels(sub_pixels),
+                        checksum: parse_checksum(line_number, words[4])?,
+                    });
+                }
+                _ => return parse_error(line_number, "unknown entry"),
+            }
+        }
+        match (level, initial_checksum) {
+            (Some(level), Some(initial_checksum)) => {
+                let replay = Self {
+                    level,
+                    initial_checksum,
+                    ticks,
+                };
+                let initial_state =
+                    replay.initial_state().map_err(|error| ReplayError::Level {
+                        line: level_line,
+                        error,
+                    })?;
+                replay
+                    .playback()
+                    .check_initial_state(&initial_state)
+                    .map_err(ReplayError::InitialState)?;
+                Ok(replay)
+            }
+            (None, _) => parse_error(0, "missing level"),
+            (_, None) => parse_error(0, "missing initial checksum"),
+        }
+    }
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ReplayError> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+    /// A game state with the replay's level loaded, ready for playback.
+    pub fn initial_state(&self) -> Result<GameState, LevelError> {
+        let size = self.level.size;
+        let mut game_state = GameState::new(vec2(size[0] as f32, size[1] as f32));
+        game_state.load_level(&self.level)?;
+        Ok(game_state)
+    }
+    pub fn playback(&self) -> Playback<'_> {
+        Playback {
+            replay: self,
+            next_tick: 0,
+        }
+    }
+    /// Runs the whole replay on a game state which has been reset to the replay's level,
+    /// returning every tick whose checksum doesn't match the recording.
+    pub fn run(&self, game_state: &mut GameState) -> Vec<Divergence> {
+        let mut playback = self.playback();
+        let mut divergences = Vec::new();
+        if let Err(divergence) = playback.check_initial_state(game_state) {
+            divergences.push(divergence);
+        }
+        while let Some(result) = playback.step(game_state) {
+            if let Err(divergence) = result {
+                divergences.push(divergence);
+            }
+        }
+        divergences
+    }
+}
+
+pub struct Playback<'a> {
+    replay: &'a Replay,
+    next_tick: usize,
+}
+
+impl<'a> Playback<'a> {
+    pub fn is_finished(&self) -> bool {
+        self.next_tick >= self.replay.ticks.len()
+    }
+    pub fn check_initial_state(&self, game_state: &GameState) -> Result<(), Divergence> {
+        let actual = game_state.checksum();
+        if actual == self.replay.initial_checksum {
+            Ok(())
+        } else {
+            Err(Divergence {
+                tick: 0,
+                expected: self.replay.initial_checksum,
+                actual,
+            })
+        }
+    }
+    /// Feeds the next recorded input into `game_state`. Returns `None` once every tick has
+    /// been played.
+    pub fn step(&mut self, game_state: &mut GameState) -> Option<Result<(), Divergence>> {
+        let tick = self.replay.ticks.get(self.next_tick)?;
+        self.next_tick += 1;
+        game_state.update(&tick.input_model);
+        let actual = game_state.checksum();
+        Some(if actual == tick.checksum {
+            Ok(())
+        } else {
+            Err(Divergence {
+                tick: self.next_tick,
+                expected: tick.checksum,
+                actual,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn demo_level() -> Level {
+        include_str!("../levels/demo.json").parse().unwrap()
+    }
+
+    fn demo() -> GameState {
+        let mut game_state = GameState::new(vec2(960., 640.));
+        game_state.load_level(&demo_level()).unwrap();
+        game_state
+    }
+
+    fn record_demo() -> Replay {
+        let mut game_state = demo();
+        let mut replay = Replay::new(demo_level(), &game_state);
+        let mut input_model = InputModel::default();
+        input_model.set_down(1.);
+        for tick in 0..120 {
+            if tick == 40 {
+                input_model.set_down(0.);
+                input_model.set_right(1.);
+            }
+            game_state.update(&input_model);
+            replay.record(&input_model, &game_state);
+        }
+        replay
+    }
+
+    #[test]
+    fn round_trip() {
+        let replay = record_demo();
+        let mut buffer = Vec::new();
+        replay.write(&mut buffer).unwrap();
+        assert_eq!(Replay::read(&buffer[..]).unwrap(), replay);
+    }
+
+    #[test]
+    fn playback_matches_recording() {
+        let replay = record_demo();
+        assert_eq!(replay.run(&mut demo()), Vec::new());
+    }
+
+    #[test]
+    fn playback_reports_divergence() {
+        let mut replay = record_demo();
+        replay.ticks[10].input_model = InputModel::default();
+        let divergences = replay.run(&mut demo());
+        assert_eq!(divergences[0].tick, 11);
+    }
+
+    #[test]
+    fn embedded_level_must_match_initial_checksum() {
+        let mut replay = record_demo();
+        assert_eq!(
+            replay.initial_state().unwrap().checksum(),
+            replay.initial_checksum
+        );
+        replay.level.entities[0].position[0] += 1.;
+        let mut buffer = Vec::new();
+        replay.write(&mut buffer).unwrap();
+        match Replay::read(&buffer[..]) {
+            Err(ReplayError::InitialState(divergence)) => assert_eq!(divergence.tick, 0),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,16 @@
+#version 150 core
+
+in vec4 v_Colour;
+in vec2 v_OffsetFromCentre;
+in float v_Radius;
+out vec4 Target;
+
+void main() {
+    // signed distance to the edge, scaled so the edge fades over about a window pixel
+    float distance = length(v_OffsetFromCentre) - v_Radius;
+    float alpha = clamp(0.5 - distance / fwidth(distance), 0, 1);
+    if (alpha == 0) {
+        discard;
+    }
+    Target = vec4(v_Colour.rgb, v_Colour.a * alpha);
+}
@@ -0,0 +1,42 @@
+#version 150 core
+
+in vec2 a_CornerZeroToOne;
+in vec2 i_Centre;
+in float i_Radius;
+in vec4 i_Colour;
+
+uniform WindowProperties {
+    vec2 u_WindowSizeInPixels;
+    vec2 u_WorldToWindowScale;
+    vec2 u_WorldToWindowOffset;
+};
+
+uniform CameraProperties {
+    vec2 u_CameraTopLeft;
+    vec2 u_CameraZoom;
+};
+
+out vec4 v_Colour;
+out vec2 v_OffsetFromCentre;
+out float v_Radius;
+
+void main() {
+
+    // one pixel of margin so the anti-aliased edge isn't clipped
+    float half_size = i_Radius + 1;
+    vec2 offset_from_centre = (a_CornerZeroToOne * 2 - 1) * half_size;
+    vec2 pixel_coord = i_Centre + offset_from_centre;
+
+    pixel_coord = (pixel_coord - u_CameraTopLeft) * u_CameraZoom;
+    pixel_coord = pixel_coord * u_WorldToWindowScale + u_WorldToWindowOffset;
+
+    vec2 screen_coord = vec2(
+        pixel_coord.x / u_WindowSizeInPixels.x * 2 - 1,
+        1 - pixel_coord.y / u_WindowSizeInPixels.y * 2);
+
+    v_Colour = i_Colour;
+    v_OffsetFromCentre = offset_from_centre;
+    v_Radius = i_Radius;
+
+    gl_Position = vec4(screen_coord, 0, 1);
+}
@@ -3,23 +3,32 @@
 in vec2 a_CornerZeroToOne;
 in vec2 i_Start;
 in vec2 i_End;
-in vec3 i_Colour;
+in float i_Thickness;
+in vec4 i_Colour;
 
 uniform WindowProperties {
     vec2 u_WindowSizeInPixels;
+    vec2 u_WorldToWindowScale;
+    vec2 u_WorldToWindowOffset;
 };
 
-out vec3 v_Colour;
+uniform CameraProperties {
+    vec2 u_CameraTopLeft;
+    vec2 u_CameraZoom;
+};
 
-const float WIDTH = 2;
+out vec4 v_Colour;
 
 void main() {
 
     vec2 start_to_end = i_End - i_Start;
-    vec2 width = normalize(vec2(-start_to_end.y, start_to_end.x)) * WIDTH;
+    vec2 width = normalize(vec2(-start_to_end.y, start_to_end.x)) * i_Thickness;
     vec2 corner = i_Start - width / 2;
     vec2 pixel_coord = corner + (start_to_end * a_CornerZeroToOne.x) + (width * a_CornerZeroToOne.y) ;
 
+    pixel_coord = (pixel_coord - u_CameraTopLeft) * u_CameraZoom;
+    pixel_coord = pixel_coord * u_WorldToWindowScale + u_WorldToWindowOffset;
+
     vec2 screen_coord = vec2(
         pixel_coord.x / u_WindowSizeInPixels.x * 2 - 1,
         1 - pixel_coord.y / u_WindowSizeInPixels.y * 2);
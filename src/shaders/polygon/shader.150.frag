@@ -0,0 +1,8 @@
+#version 150 core
+
+in vec4 v_Colour;
+out vec4 Target;
+
+void main() {
+    Target = v_Colour;
+}
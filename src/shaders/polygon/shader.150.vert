@@ -0,0 +1,31 @@
+#version 150 core
+
+in vec2 a_Position;
+in vec4 a_Colour;
+
+uniform WindowProperties {
+    vec2 u_WindowSizeInPixels;
+    vec2 u_WorldToWindowScale;
+    vec2 u_WorldToWindowOffset;
+};
+
+uniform CameraProperties {
+    vec2 u_CameraTopLeft;
+    vec2 u_CameraZoom;
+};
+
+out vec4 v_Colour;
+
+void main() {
+
+    vec2 pixel_coord = (a_Position - u_CameraTopLeft) * u_CameraZoom;
+    pixel_coord = pixel_coord * u_WorldToWindowScale + u_WorldToWindowOffset;
+
+    vec2 screen_coord = vec2(
+        pixel_coord.x / u_WindowSizeInPixels.x * 2 - 1,
+        1 - pixel_coord.y / u_WindowSizeInPixels.y * 2);
+
+    v_Colour = a_Colour;
+
+    gl_Position = vec4(screen_coord, 0, 1);
+}
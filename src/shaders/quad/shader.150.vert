@@ -3,19 +3,29 @@
 in vec2 a_CornerZeroToOne;
 in vec2 i_PositionOfTopLeftInPixels;
 in vec2 i_DimensionsInPixels;
-in vec3 i_Colour;
+in vec4 i_Colour;
 
 uniform WindowProperties {
     vec2 u_WindowSizeInPixels;
+    vec2 u_WorldToWindowScale;
+    vec2 u_WorldToWindowOffset;
 };
 
-out vec3 v_Colour;
+uniform CameraProperties {
+    vec2 u_CameraTopLeft;
+    vec2 u_CameraZoom;
+};
+
+out vec4 v_Colour;
 
 void main() {
 
     vec2 pixel_offset = a_CornerZeroToOne * i_DimensionsInPixels;
     vec2 pixel_coord = i_PositionOfTopLeftInPixels + pixel_offset;
 
+    pixel_coord = (pixel_coord - u_CameraTopLeft) * u_CameraZoom;
+    pixel_coord = pixel_coord * u_WorldToWindowScale + u_WorldToWindowOffset;
+
     vec2 screen_coord = vec2(
         pixel_coord.x / u_WindowSizeInPixels.x * 2 - 1,
         1 - pixel_coord.y / u_WindowSizeInPixels.y * 2);
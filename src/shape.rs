@@ -2,10 +2,12 @@ use aabb::Aabb;
 use physics_num::{self, PhysicsNum};
 use axis_aligned_rect::AxisAlignedRect;
 use best::BestMap;
-use cgmath::{Vector2, vec2};
+use vector::{Vector2, vec2};
 use collision::{self, Collision};
 use line_segment::LineSegment;
 use num::{One, Zero};
+use alloc::rc::Rc;
+use tile_map::TileMap;
 
 fn for_each_single_direction_intersection<A, B, F, N>(
     shape: &A,
@@ -131,6 +133,7 @@ pub struct CollisionInfo<N> {
 pub enum Shape<N: PhysicsNum> {
     AxisAlignedRect(AxisAlignedRect<N>),
     LineSegment(LineSegment<N>),
+    TileMap(Rc<TileMap<N>>),
 }
 
 impl<N: PhysicsNum> Shape<N> {
@@ -138,6 +141,16 @@ impl<N: PhysicsNum> Shape<N> {
         match self {
             &Shape::AxisAlignedRect(ref rect) => rect.aabb(top_left),
             &Shape::LineSegment(ref line_segment) => line_segment.aabb(top_left),
+            &Shape::TileMap(ref tile_map) => tile_map.aabb(top_left),
+        }
+    }
+    pub fn is_intersecting_aabb(&self, position: Vector2<N>, aabb: &Aabb<N>) -> bool {
+        match self {
+            &Shape::AxisAlignedRect(ref rect) => rect.aabb(position).is_intersecting(aabb),
+            &Shape::LineSegment(ref line_segment) => line_segment
+                .add_vector(position)
+                .is_intersecting_aabb(aabb),
+            &Shape::TileMap(ref tile_map) => tile_map.is_intersecting_aabb(position, aabb),
         }
     }
     pub fn movement_collision_test(
@@ -162,8 +175,19 @@ impl<N: PhysicsNum> Shape<N> {
                     stationary_position,
                     movement_vector,
                 ),
+                &Shape::TileMap(ref stationary) => {
+                    let swept = moving
+                        .aabb(position)
+                        .union(&moving.aabb(position + movement_vector));
+                    moving.movement_collision_test(
+                        position,
+                        &stationary.region(stationary_position, &swept),
+                        stationary_position,
+                        movement_vector,
+                    )
+                }
             },
-            &Shape::LineSegment(_) => panic!(),
+            &Shape::LineSegment(_) | &Shape::TileMap(_) => panic!(),
         }
     }
 }
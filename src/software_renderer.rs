@@ -0,0 +1,355 @@
+use vector::{Vector2, vec2};
+use draw::DrawTarget;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+const MAX_STORED_BLOCK_LEN: usize = 0xffff;
+
+fn colour_channel(value: f32) -> u8 {
+    (value.max(0.).min(1.) * 255.).round() as u8
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn write_u32<W: Write>(writer: &mut W, value: u32) -> io::Result<()> {
+    writer.write_all(&[
+        (value >> 24) as u8,
+        (value >> 16) as u8,
+        (value >> 8) as u8,
+        value as u8,
+    ])
+}
+
+fn write_chunk<W: Write>(writer: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    write_u32(writer, data.len() as u32)?;
+    let mut crc_input = Vec::with_capacity(4 + data.len());
+    crc_input.extend_from_slice(kind);
+    crc_input.extend_from_slice(data);
+    writer.write_all(&crc_input)?;
+    write_u32(writer, crc32(&crc_input))
+}
+
+/// A zlib stream of uncompressed deflate blocks. Images are only written for tests and bug
+/// reports, so simplicity matters more than size.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut output = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_STORED_BLOCK_LEN).peekable();
+    if blocks.peek().is_none() {
+        output.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_final = blocks.peek().is_none();
+        let len = block.len() as u16;
+        output.push(is_final as u8);
+        output.extend_from_slice(&[len as u8, (len >> 8) as u8, !len as u8, (!len >> 8) as u8]);
+        output.extend_from_slice(block);
+    }
+    let adler = adler32(data);
+    output.extend_from_slice(&[
+        (adler >> 24) as u8,
+        (adler >> 16) as u8,
+        (adler >> 8) as u8,
+        adler as u8,
+    ]);
+    output
+}
+
+/// Draws the same primitives as `graphics::FrameUpdater` into an RGBA buffer in memory, so
+/// frames can be captured without a GPU. A pixel is covered by a shape if its centre is.
+#[derive(Debug, Clone)]
+pub struct SoftwareRenderer {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl SoftwareRenderer {
+    /// Starts opaque black, like the window.
+    pub fn new(width: u32, height: u32) -> Self {
+        let mut renderer = Self {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 4],
+        };
+        renderer.clear([0., 0., 0., 1.]);
+        renderer
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    /// Rows of RGBA pixels, from the top.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let index = (y as usize * self.width as usize + x as usize) * 4;
+        [
+            self.pixels[index],
+            self.pixels[index + 1],
+            self.pixels[index + 2],
+            self.pixels[index + 3],
+        ]
+    }
+    pub fn clear(&mut self, colour: [f32; 4]) {
+        let rgba = [
+            colour_channel(colour[0]),
+            colour_channel(colour[1]),
+            colour_channel(colour[2]),
+            colour_channel(colour[3]),
+        ];
+        for pixel in self.pixels.chunks_mut(4) {
+            pixel.copy_from_slice(&rgba);
+        }
+    }
+    /// Blends `colour` over the pixel, like the pipelines' alpha blending.
+    fn set_pixel(&mut self, x: u32, y: u32, colour: [f32; 4]) {
+        let index = (y as usize * self.width as usize + x as usize) * 4;
+        let alpha = colour[3].max(0.).min(1.);
+        for channel in 0..3 {
+            let dst = self.pixels[index + channel] as f32 / 255.;
+            self.pixels[index + channel] =
+                colour_channel(colour[channel] * alpha + dst * (1. - alpha));
+        }
+        let dst_alpha = self.pixels[index + 3] as f32 / 255.;
+        self.pixels[index + 3] = colour_channel(alpha + dst_alpha * (1. - alpha));
+    }
+    /// The pixels whose centres might lie in `[min, max)`, clipped to the buffer.
+    fn pixel_range(&self, min: Vector2<f32>, max: Vector2<f32>) -> (u32, u32, u32, u32) {
+        let clip = |value: f32, size: u32| (value - 0.5).ceil().max(0.).min(size as f32) as u32;
+        (
+            clip(min.x, self.width),
+            clip(max.x, self.width),
+            clip(min.y, self.height),
+            clip(max.y, self.height),
+        )
+    }
+    /// Writes the buffer as an uncompressed 8-bit RGBA PNG. The output depends only on the
+    /// pixels, so it can be compared byte for byte against a golden image.
+    pub fn write_png<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&PNG_SIGNATURE)?;
+        let mut header = Vec::with_capacity(13);
+        write_u32(&mut header, self.width)?;
+        write_u32(&mut header, self.height)?;
+        // 8 bits per channel, RGBA, deflate, no filtering, no interlacing
+        header.extend_from_slice(&[8, 6, 0, 0, 0]);
+        write_chunk(&mut writer, b"IHDR", &header)?;
+        let row_len = self.width as usize * 4;
+        let mut scanlines = Vec::with_capacity((row_len + 1) * self.height as usize);
+        for row in self.pixels.chunks(row_len.max(1)) {
+            scanlines.push(0);
+            scanlines.extend_from_slice(row);
+        }
+        write_chunk(&mut writer, b"IDAT", &zlib_stored(&scanlines))?;
+        write_chunk(&mut writer, b"IEND", &[])
+    }
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_png(&mut writer)?;
+        writer.flush()
+    }
+}
+
+impl DrawTarget for SoftwareRenderer {
+    fn axis_aligned_rect(
+        &mut self,
+        top_left: Vector2<f32>,
+        size: Vector2<f32>,
+        colour: [f32; 4],
+    ) {
+        let (x_min, x_max, y_min, y_max) = self.pixel_range(top_left, top_left + size);
+        for y in y_min..y_max {
+            for x in x_min..x_max {
+                self.set_pixel(x, y, colour);
+            }
+        }
+    }
+    /// Drawn as a quad `thickness` pixels wide centred on the line, like the line segment
+    /// shader.
+    fn line_segment(
+        &mut self,
+        start: Vector2<f32>,
+        end: Vector2<f32>,
+        thickness: f32,
+        colour: [f32; 4],
+    ) {
+        let start_to_end = end - start;
+        let length2 = start_to_end.magnitude2();
+        if length2 == 0. {
+            return;
+        }
+        let half_width = vec2(thickness / 2., thickness / 2.);
+        let min = vec2(start.x.min(end.x), start.y.min(end.y)) - half_width;
+        let max = vec2(start.x.max(end.x), start.y.max(end.y)) + half_width;
+        let (x_min, x_max, y_min, y_max) = self.pixel_range(min, max);
+        let length = length2.sqrt();
+        for y in y_min..y_max {
+            for x in x_min..x_max {
+                let to_centre = vec2(x as f32 + 0.5, y as f32 + 0.5) - start;
+                let along = to_centre.dot(start_to_end) / length2;
+                let across = (start_to_end.x * to_centre.y - start_to_end.y * to_centre.x) / length;
+                if along >= 0. && along < 1. && across.abs() < thickness / 2. {
+                    self.set_pixel(x, y, colour);
+                }
+            }
+        }
+    }
+    fn convex_polygon(&mut self, vertices: &[Vector2<f32>], colour: [f32; 4]) {
+        if vertices.len() < 3 {
+            return;
+        }
+        let mut min = vertices[0];
+        let mut max = vertices[0];
+        for v in vertices.iter() {
+            min = vec2(min.x.min(v.x), min.y.min(v.y));
+            max = vec2(max.x.max(v.x), max.y.max(v.y));
+        }
+        let (x_min, x_max, y_min, y_max) = self.pixel_range(min, max);
+        for y in y_min..y_max {
+            for x in x_min..x_max {
+                let centre = vec2(x as f32 + 0.5, y as f32 + 0.5);
+                // inside if the centre is on the same side of every edge, whichever way
+                // round the vertices go
+                let mut any_positive = false;
+                let mut any_negative = false;
+                for (i, &start) in vertices.iter().enumerate() {
+                    let end = vertices[(i + 1) % vertices.len()];
+                    let edge = end - start;
+                    let to_centre = centre - start;
+                    let cross = edge.x * to_centre.y - edge.y * to_centre.x;
+                    any_positive |= cross > 0.;
+                    any_negative |= cross < 0.;
+                }
+                if !(any_positive && any_negative) {
+                    self.set_pixel(x, y, colour);
+                }
+            }
+        }
+    }
+    fn circle(&mut self, centre: Vector2<f32>, radius: f32, colour: [f32; 4]) {
+        let half_size = vec2(radius, radius);
+        let (x_min, x_max, y_min, y_max) =
+            self.pixel_range(centre - half_size, centre + half_size);
+        for y in y_min..y_max {
+            for x in x_min..x_max {
+                let to_centre = vec2(x as f32 + 0.5, y as f32 + 0.5) - centre;
+                if to_centre.magnitude2() < radius * radius {
+                    self.set_pixel(x, y, colour);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const RED: [f32; 4] = [1., 0., 0., 1.];
+
+    fn covered(renderer: &SoftwareRenderer) -> Vec<(u32, u32)> {
+        let mut covered = Vec::new();
+        for y in 0..renderer.height() {
+            for x in 0..renderer.width() {
+                if renderer.pixel(x, y) != [0, 0, 0, 255] {
+                    covered.push((x, y));
+                }
+            }
+        }
+        covered
+    }
+
+    #[test]
+    fn rect_covers_pixel_centres() {
+        let mut renderer = SoftwareRenderer::new(8, 8);
+        renderer.axis_aligned_rect(vec2(1.5, 2.), vec2(2., 1.6), RED);
+        assert_eq!(covered(&renderer), vec![(1, 2), (2, 2), (1, 3), (2, 3)]);
+        assert_eq!(renderer.pixel(1, 2), [255, 0, 0, 255]);
+        renderer.axis_aligned_rect(vec2(-10., -10.), vec2(100., 100.), RED);
+        assert_eq!(covered(&renderer).len(), 64);
+    }
+
+    #[test]
+    fn line_thickness() {
+        let mut renderer = SoftwareRenderer::new(8, 8);
+        renderer.line_segment(vec2(1., 4.), vec2(5., 4.), 2., RED);
+        let mut expected = Vec::new();
+        for &y in [3, 4].iter() {
+            for x in 1..5 {
+                expected.push((x, y));
+            }
+        }
+        assert_eq!(covered(&renderer), expected);
+        let mut renderer = SoftwareRenderer::new(8, 8);
+        renderer.line_segment(vec2(1., 4.), vec2(5., 4.), 4., RED);
+        assert_eq!(covered(&renderer).len(), 16);
+    }
+
+    #[test]
+    fn outline_and_alpha() {
+        let mut renderer = SoftwareRenderer::new(8, 8);
+        renderer.filled_rect_outline(vec2(0., 0.), vec2(4., 4.), 1., RED);
+        assert_eq!(covered(&renderer).len(), 12);
+        assert_eq!(renderer.pixel(1, 1), [0, 0, 0, 255]);
+        renderer.axis_aligned_rect(vec2(0., 0.), vec2(1., 1.), [0., 0., 1., 0.5]);
+        assert_eq!(renderer.pixel(0, 0), [128, 0, 128, 255]);
+    }
+
+    #[test]
+    fn polygon_and_circle() {
+        let mut renderer = SoftwareRenderer::new(8, 8);
+        // a triangle covering the pixels below the diagonal, wound anticlockwise
+        renderer.convex_polygon(&[vec2(0., 0.), vec2(4., 4.), vec2(0., 4.)], RED);
+        let mut expected = Vec::new();
+        for y in 0..4 {
+            for x in 0..(y + 1) {
+                expected.push((x, y));
+            }
+        }
+        assert_eq!(covered(&renderer), expected);
+        let mut renderer = SoftwareRenderer::new(8, 8);
+        renderer.circle(vec2(4., 4.), 1.5, RED);
+        assert_eq!(covered(&renderer), vec![(3, 3), (4, 3), (3, 4), (4, 4)]);
+    }
+
+    #[test]
+    fn png() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        let mut renderer = SoftwareRenderer::new(3, 2);
+        renderer.axis_aligned_rect(vec2(0., 0.), vec2(1., 1.), RED);
+        let mut png = Vec::new();
+        renderer.write_png(&mut png).unwrap();
+        assert_eq!(&png[..8], &PNG_SIGNATURE);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[png.len() - 12..], &[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]);
+        // signature, IHDR, IDAT of (zlib header, block header, 2 rows of 13 bytes,
+        // adler), IEND
+        assert_eq!(png.len(), 8 + 25 + 12 + 2 + 5 + 26 + 4 + 12);
+        let first_row = &png[8 + 25 + 8 + 2 + 5..][..13];
+        assert_eq!(first_row, &[0, 255, 0, 0, 255, 0, 0, 0, 255, 0, 0, 0, 255]);
+    }
+}
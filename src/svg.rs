@@ -0,0 +1,236 @@
+//! Exports the world as an SVG image at pixel coordinates, for attaching to bug reports.
+
+use vector::{Vector2, vec2};
+use debug_overlay;
+use draw::DrawTarget;
+use game::GameState;
+use pixel_num::sub_pixel_i64::vector_to_f32_pixel;
+use std::fmt::Write as FmtWrite;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SvgOptions {
+    pub quad_tree: bool,
+    pub velocity: bool,
+}
+
+fn css_colour(colour: [f32; 4]) -> String {
+    let channel = |value: f32| (value.max(0.).min(1.) * 255.).round() as u8;
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        channel(colour[0]),
+        channel(colour[1]),
+        channel(colour[2])
+    )
+}
+
+/// Paints `attribute` ("fill" or "stroke") with `colour`, adding an opacity attribute
+/// only when it's translucent.
+fn paint(attribute: &str, colour: [f32; 4]) -> String {
+    let alpha = colour[3].max(0.).min(1.);
+    if alpha < 1. {
+        format!(
+            r#"{0}="{1}" {0}-opacity="{2}""#,
+            attribute,
+            css_colour(colour),
+            alpha
+        )
+    } else {
+        format!(r#"{}="{}""#, attribute, css_colour(colour))
+    }
+}
+
+/// An SVG document built up from draw calls.
+#[derive(Debug, Clone)]
+pub struct Svg {
+    size: Vector2<f32>,
+    elements: String,
+}
+
+impl Svg {
+    /// Starts with a black background, like the window.
+    pub fn new(size: Vector2<f32>) -> Self {
+        let mut svg = Self {
+            size,
+            elements: String::new(),
+        };
+        svg.axis_aligned_rect(vec2(0., 0.), size, [0., 0., 0., 1.]);
+        svg
+    }
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(
+            writer,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+            self.size.x, self.size.y
+        )?;
+        writer.write_all(self.elements.as_bytes())?;
+        writeln!(writer, "</svg>")
+    }
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+}
+
+impl DrawTarget for Svg {
+    fn axis_aligned_rect(
+        &mut self,
+        top_left: Vector2<f32>,
+        size: Vector2<f32>,
+        colour: [f32; 4],
+    ) {
+        writeln!(
+            self.elements,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" {}/>"#,
+            top_left.x,
+            top_left.y,
+            size.x,
+            size.y,
+            paint("fill", colour)
+        ).expect("Failed to write to string");
+    }
+    fn line_segment(
+        &mut self,
+        start: Vector2<f32>,
+        end: Vector2<f32>,
+        thickness: f32,
+        colour: [f32; 4],
+    ) {
+        writeln!(
+            self.elements,
+            r#"<line x1="{}" y1="{}" x2="{}" y2="{}" {} stroke-width="{}"/>"#,
+            start.x,
+            start.y,
+            end.x,
+            end.y,
+            paint("stroke", colour),
+            thickness
+        ).expect("Failed to write to string");
+    }
+    fn convex_polygon(&mut self, vertices: &[Vector2<f32>], colour: [f32; 4]) {
+        if vertices.len() < 3 {
+            return;
+        }
+        let points = vertices
+            .iter()
+            .map(|v| format!("{},{}", v.x, v.y))
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(
+            self.elements,
+            r#"<polygon points="{}" {}/>"#,
+            points,
+            paint("fill", colour)
+        ).expect("Failed to write to string");
+    }
+    fn circle(&mut self, centre: Vector2<f32>, radius: f32, colour: [f32; 4]) {
+        writeln!(
+            self.elements,
+            r#"<circle cx="{}" cy="{}" r="{}" {}/>"#,
+            centre.x,
+            centre.y,
+            radius,
+            paint("fill", colour)
+        ).expect("Failed to write to string");
+    }
+    /// Drawn as a single stroked rectangle so outlines don't hide the shapes underneath.
+    fn rect_outline(
+        &mut self,
+        top_left: Vector2<f32>,
+        size: Vector2<f32>,
+        thickness: f32,
+        colour: [f32; 4],
+    ) {
+        writeln!(
+            self.elements,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" fill="none" {} stroke-width="{}"/>"#,
+            top_left.x,
+            top_left.y,
+            size.x,
+            size.y,
+            paint("stroke", colour),
+            thickness
+        ).expect("Failed to write to string");
+    }
+    /// Drawn as a single stroked rectangle, with the stroke inset so it stays inside the
+    /// rectangle like the default filled outline.
+    fn filled_rect_outline(
+        &mut self,
+        top_left: Vector2<f32>,
+        size: Vector2<f32>,
+        thickness: f32,
+        colour: [f32; 4],
+    ) {
+        let thickness = thickness.min(size.x / 2.).min(size.y / 2.);
+        writeln!(
+            self.elements,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" fill="none" {} stroke-width="{}"/>"#,
+            top_left.x + thickness / 2.,
+            top_left.y + thickness / 2.,
+            size.x - thickness,
+            size.y - thickness,
+            paint("stroke", colour),
+            thickness
+        ).expect("Failed to write to string");
+    }
+}
+
+/// Draws every entity, followed by any overlays enabled in `options`.
+pub fn export(game_state: &GameState, options: &SvgOptions) -> Svg {
+    let mut svg = Svg::new(vector_to_f32_pixel(game_state.size()));
+    game_state.draw(&mut svg);
+    if options.quad_tree {
+        debug_overlay::draw_quad_tree(game_state, &mut svg);
+    }
+    if options.velocity {
+        debug_overlay::draw_velocities(game_state, &mut svg);
+    }
+    svg
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use game::InputModel;
+
+    fn to_string(svg: &Svg) -> String {
+        let mut output = Vec::new();
+        svg.write(&mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn demo() {
+        let mut game_state = GameState::new(vec2(960., 640.));
+        game_state.init_demo();
+        let plain = to_string(&export(&game_state, &SvgOptions::default()));
+        assert!(
+            plain.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="960""#)
+        );
+        assert!(plain.ends_with("</svg>\n"));
+        assert!(plain.contains(
+            r##"<rect x="200" y="50" width="32" height="64" fill="#ff0000"/>"##
+        ));
+        assert!(plain
+            .contains(r##"<line x1="20" y1="20" x2="70" y2="120" stroke="#00ff00""##));
+        assert!(!plain.contains("fill=\"none\""));
+
+        let mut input_model = InputModel::default();
+        input_model.set_right(1.);
+        game_state.update(&input_model);
+        let options = SvgOptions {
+            quad_tree: true,
+            velocity: true,
+        };
+        let debug = to_string(&export(&game_state, &options));
+        assert!(debug.contains(
+            r##"<rect x="0" y="0" width="960" height="640" fill="none" stroke="#808080""##
+        ));
+        // the player moves 4 pixels a tick, drawn over 8 ticks from its centre
+        assert!(debug
+            .contains(r##"<line x1="220" y1="82" x2="252" y2="82" stroke="#ffffff""##));
+    }
+}
@@ -0,0 +1,344 @@
+use aabb::Aabb;
+use alloc::vec::Vec;
+use vector::{Vector2, vec2};
+use line_segment::LineSegment;
+use num::{One, Zero};
+use physics_num::PhysicsNum;
+use shape::Collide;
+
+/// Cells `start..end` along a boundary line which form a single exposed edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Run {
+    start: usize,
+    end: usize,
+}
+
+/// Boundary lines to consider, inclusive at both ends.
+#[derive(Debug, Clone, Copy)]
+struct LineRange {
+    x_min: usize,
+    x_max: usize,
+    y_min: usize,
+    y_max: usize,
+}
+
+/// A grid of solid cells which collides only along the exposed boundary between solid
+/// and empty cells. Adjacent boundaries are merged, so there are no seams between
+/// neighbouring cells for things to catch on.
+#[derive(Debug, Clone)]
+pub struct TileMap<N> {
+    tile_size: Vector2<N>,
+    width: usize,
+    height: usize,
+    solid: Vec<bool>,
+    // Indexed by the row or column of the boundary line the edges lie on.
+    top: Vec<Vec<Run>>,
+    bottom: Vec<Vec<Run>>,
+    left: Vec<Vec<Run>>,
+    right: Vec<Vec<Run>>,
+}
+
+fn floor_div<N: PhysicsNum>(a: N, b: N) -> i64 {
+    let quotient = a / b;
+    let quotient = if a < Zero::zero() && !(a % b).is_zero() {
+        quotient - One::one()
+    } else {
+        quotient
+    };
+    quotient.to_i64().expect("tile index out of range")
+}
+
+fn clamp_line(line: i64, max: usize) -> usize {
+    line.max(0).min(max as i64) as usize
+}
+
+fn push_cell(runs: &mut Vec<Run>, cell: usize) {
+    if let Some(run) = runs.last_mut() {
+        if run.end == cell {
+            run.end += 1;
+            return;
+        }
+    }
+    runs.push(Run {
+        start: cell,
+        end: cell + 1,
+    });
+}
+
+impl<N: PhysicsNum> TileMap<N> {
+    /// `solid` is indexed by `y * width + x`.
+    pub fn new(tile_size: Vector2<N>, width: usize, height: usize, solid: Vec<bool>) -> Self {
+        assert_eq!(solid.len(), width * height, "tile map has the wrong number of cells");
+        let mut tile_map = Self {
+            tile_size,
+            width,
+            height,
+            solid,
+            top: vec![Vec::new(); height + 1],
+            bottom: vec![Vec::new(); height + 1],
+            left: vec![Vec::new(); width + 1],
+            right: vec![Vec::new(); width + 1],
+        };
+        for y in 0..height {
+            for x in 0..width {
+                if !tile_map.is_solid(x as i64, y as i64) {
+                    continue;
+                }
+                let (xi, yi) = (x as i64, y as i64);
+                if !tile_map.is_solid(xi, yi - 1) {
+                    push_cell(&mut tile_map.top[y], x);
+                }
+                if !tile_map.is_solid(xi, yi + 1) {
+                    push_cell(&mut tile_map.bottom[y + 1], x);
+                }
+            }
+        }
+        for x in 0..width {
+            for y in 0..height {
+                if !tile_map.is_solid(x as i64, y as i64) {
+                    continue;
+                }
+                let (xi, yi) = (x as i64, y as i64);
+                if !tile_map.is_solid(xi - 1, yi) {
+                    push_cell(&mut tile_map.left[x], y);
+                }
+                if !tile_map.is_solid(xi + 1, yi) {
+                    push_cell(&mut tile_map.right[x + 1], y);
+                }
+            }
+        }
+        tile_map
+    }
+    pub fn tile_size(&self) -> Vector2<N> {
+        self.tile_size
+    }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    /// Cells outside the map are empty.
+    pub fn is_solid(&self, x: i64, y: i64) -> bool {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return false;
+        }
+        self.solid[y as usize * self.width + x as usize]
+    }
+    fn cell_coord(&self, x: usize, y: usize) -> Vector2<N> {
+        vec2(
+            self.tile_size.x * N::from(x).expect("tile map too large"),
+            self.tile_size.y * N::from(y).expect("tile map too large"),
+        )
+    }
+    fn full_range(&self) -> LineRange {
+        LineRange {
+            x_min: 0,
+            x_max: self.width,
+            y_min: 0,
+            y_max: self.height,
+        }
+    }
+    fn line_range(&self, top_left: Vector2<N>, aabb: &Aabb<N>) -> LineRange {
+        let min = aabb.top_left() - top_left;
+        let max = min + aabb.size();
+        LineRange {
+            x_min: clamp_line(floor_div(min.x, self.tile_size.x), self.width),
+            x_max: clamp_line(floor_div(max.x, self.tile_size.x) + 1, self.width),
+            y_min: clamp_line(floor_div(min.y, self.tile_size.y), self.height),
+            y_max: clamp_line(floor_div(max.y, self.tile_size.y) + 1, self.height),
+        }
+    }
+    /// Restricts collision to the edges near `aabb`, for a map whose top left is at
+    /// `top_left`.
+    pub fn region(&self, top_left: Vector2<N>, aabb: &Aabb<N>) -> TileMapRegion<'_, N> {
+        TileMapRegion {
+            tile_map: self,
+            range: self.line_range(top_left, aabb),
+        }
+    }
+    pub fn is_intersecting_aabb(&self, top_left: Vector2<N>, aabb: &Aabb<N>) -> bool {
+        let range = self.line_range(top_left, aabb);
+        for y in range.y_min.saturating_sub(1)..range.y_max {
+            for x in range.x_min.saturating_sub(1)..range.x_max {
+                if self.is_solid(x as i64, y as i64)
+                    && Aabb::new(top_left + self.cell_coord(x, y), self.tile_size)
+                        .is_intersecting(aabb)
+                {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+    /// Calls `f` with the top left and size of each horizontal run of solid cells.
+    pub fn for_each_solid_run<F: FnMut(Vector2<N>, Vector2<N>)>(&self, mut f: F) {
+        for y in 0..self.height {
+            let mut runs = Vec::new();
+            for x in 0..self.width {
+                if self.is_solid(x as i64, y as i64) {
+                    push_cell(&mut runs, x);
+                }
+            }
+            for run in runs {
+                let size = self.cell_coord(run.end - run.start, 1);
+                f(self.cell_coord(run.start, y), size);
+            }
+        }
+    }
+    fn for_each_edge_in_range<F>(&self, direction: Vector2<N>, range: LineRange, mut f: F)
+    where
+        F: FnMut(LineSegment<N>),
+    {
+        let horizontal = |runs: &Vec<Vec<Run>>, f: &mut F, reverse: bool| {
+            for y in range.y_min..(range.y_max + 1) {
+                for run in runs[y].iter() {
+                    if run.start <= range.x_max && run.end >= range.x_min {
+                        let start = self.cell_coord(run.start, y);
+                        let end = self.cell_coord(run.end, y);
+                        if reverse {
+                            f(LineSegment::new(end, start));
+                        } else {
+                            f(LineSegment::new(start, end));
+                        }
+                    }
+                }
+            }
+        };
+        let vertical = |runs: &Vec<Vec<Run>>, f: &mut F, reverse: bool| {
+            for x in range.x_min..(range.x_max + 1) {
+                for run in runs[x].iter() {
+                    if run.start <= range.y_max && run.end >= range.y_min {
+                        let start = self.cell_coord(x, run.start);
+                        let end = self.cell_coord(x, run.end);
+                        if reverse {
+                            f(LineSegment::new(end, start));
+                        } else {
+                            f(LineSegment::new(start, end));
+                        }
+                    }
+                }
+            }
+        };
+        // Edges wind the same way as those of an `AxisAlignedRect`.
+        if direction.y >= Zero::zero() {
+            horizontal(&self.bottom, &mut f, true);
+        }
+        if direction.y <= Zero::zero() {
+            horizontal(&self.top, &mut f, false);
+        }
+        if direction.x >= Zero::zero() {
+            vertical(&self.right, &mut f, false);
+        }
+        if direction.x <= Zero::zero() {
+            vertical(&self.left, &mut f, true);
+        }
+    }
+    fn for_each_vertex_in_range<F>(&self, direction: Vector2<N>, range: LineRange, mut f: F)
+    where
+        F: FnMut(Vector2<N>),
+    {
+        self.for_each_edge_in_range(direction, range, |edge| {
+            f(edge.start);
+            f(edge.end);
+        });
+    }
+}
+
+impl<N: PhysicsNum> Collide<N> for TileMap<N> {
+    fn aabb(&self, top_left: Vector2<N>) -> Aabb<N> {
+        Aabb::new(top_left, self.cell_coord(self.width, self.height))
+    }
+    fn for_each_edge_facing<F: FnMut(LineSegment<N>)>(&self, direction: Vector2<N>, f: F) {
+        self.for_each_edge_in_range(direction, self.full_range(), f);
+    }
+    fn for_each_vertex_facing<F: FnMut(Vector2<N>)>(&self, direction: Vector2<N>, f: F) {
+        self.for_each_vertex_in_range(direction, self.full_range(), f);
+    }
+}
+
+/// The part of a `TileMap` near some other shape, found by looking up the grid rather
+/// than visiting every edge.
+pub struct TileMapRegion<'a, N: 'a> {
+    tile_map: &'a TileMap<N>,
+    range: LineRange,
+}
+
+impl<'a, N: PhysicsNum> Collide<N> for TileMapRegion<'a, N> {
+    fn aabb(&self, top_left: Vector2<N>) -> Aabb<N> {
+        self.tile_map.aabb(top_left)
+    }
+    fn for_each_edge_facing<F: FnMut(LineSegment<N>)>(&self, direction: Vector2<N>, f: F) {
+        self.tile_map
+            .for_each_edge_in_range(direction, self.range, f);
+    }
+    fn for_each_vertex_facing<F: FnMut(Vector2<N>)>(&self, direction: Vector2<N>, f: F) {
+        self.tile_map
+            .for_each_vertex_in_range(direction, self.range, f);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axis_aligned_rect::AxisAlignedRect;
+
+    fn tile_map(rows: &[&str]) -> TileMap<i64> {
+        let solid = rows.iter()
+            .flat_map(|row| row.chars().map(|c| c == '#'))
+            .collect();
+        TileMap::new(vec2(10, 10), rows[0].len(), rows.len(), solid)
+    }
+
+    fn edges(tile_map: &TileMap<i64>, direction: Vector2<i64>) -> Vec<(Vector2<i64>, Vector2<i64>)> {
+        let mut edges = Vec::new();
+        tile_map.for_each_edge_facing(direction, |edge| edges.push((edge.start, edge.end)));
+        edges
+    }
+
+    #[test]
+    fn merges_edges() {
+        let tile_map = tile_map(&["....", "###.", "####"]);
+        assert_eq!(
+            edges(&tile_map, vec2(1, -1)),
+            vec![
+                (vec2(0, 10), vec2(30, 10)),
+                (vec2(30, 20), vec2(40, 20)),
+                (vec2(30, 10), vec2(30, 20)),
+                (vec2(40, 20), vec2(40, 30)),
+            ]
+        );
+        assert_eq!(
+            edges(&tile_map, vec2(-1, 1)),
+            vec![(vec2(40, 30), vec2(0, 30)), (vec2(0, 30), vec2(0, 10))]
+        );
+    }
+
+    #[test]
+    fn collides_with_merged_edges() {
+        let tile_map = tile_map(&["....", "####"]);
+        let rect = AxisAlignedRect::new(vec2(5, 5));
+        assert!(
+            rect.movement_collision_test(vec2(2, 4), &tile_map, vec2(0, 0), vec2(30, 0))
+                .is_none()
+        );
+        let collision = rect.movement_collision_test(
+            vec2(2, 0),
+            &tile_map,
+            vec2(0, 0),
+            vec2(0, 20),
+        ).unwrap();
+        assert_eq!(collision.allowed_movement, vec2(0, 4));
+    }
+
+    #[test]
+    fn region_only_visits_nearby_edges() {
+        let tile_map = tile_map(&["#...#", ".....", "#...#"]);
+        let region = tile_map.region(vec2(100, 100), &Aabb::new(vec2(101, 101), vec2(5, 5)));
+        let mut edges = Vec::new();
+        region.for_each_edge_facing(vec2(1, 1), |edge| edges.push(edge.start));
+        assert_eq!(edges, vec![vec2(10, 10), vec2(10, 0)]);
+        assert!(tile_map.is_intersecting_aabb(vec2(100, 100), &Aabb::new(vec2(95, 95), vec2(5, 5))));
+        assert!(!tile_map.is_intersecting_aabb(vec2(100, 100), &Aabb::new(vec2(115, 111), vec2(5, 5))));
+    }
+}
@@ -0,0 +1,527 @@
+use level::{EntityDesc, Flag, Level, LevelError, ShapeDesc};
+use serde::de::IgnoredAny;
+use serde_json::{self, Value};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+/// Masks off the flip and rotation flags in the top four bits of a gid.
+const GID_MASK: u32 = 0x0fff_ffff;
+const DEFAULT_TILE_COLOUR: [f32; 3] = [0.5, 0.5, 0.5];
+const DEFAULT_OBJECT_COLOUR: [f32; 3] = [1., 1., 1.];
+
+#[derive(Debug, Deserialize)]
+struct Map {
+    orientation: String,
+    width: u32,
+    height: u32,
+    tilewidth: u32,
+    tileheight: u32,
+    #[serde(default)]
+    infinite: bool,
+    layers: Vec<Layer>,
+    #[serde(default)]
+    tilesets: Vec<Tileset>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum TileData {
+    Csv(Vec<u32>),
+    /// Base64 encoded data, which is rejected, so its contents are skipped.
+    Encoded(IgnoredAny),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Layer {
+    TileLayer {
+        name: String,
+        width: u32,
+        height: u32,
+        data: TileData,
+        #[serde(default)]
+        properties: Vec<Property>,
+        #[serde(default)]
+        offsetx: f64,
+        #[serde(default)]
+        offsety: f64,
+    },
+    ObjectGroup {
+        objects: Vec<Object>,
+        #[serde(default)]
+        properties: Vec<Property>,
+        #[serde(default)]
+        offsetx: f64,
+        #[serde(default)]
+        offsety: f64,
+    },
+    ImageLayer,
+    Group {
+        layers: Vec<Layer>,
+        #[serde(default)]
+        offsetx: f64,
+        #[serde(default)]
+        offsety: f64,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct Point {
+    x: f64,
+    y: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Object {
+    id: u32,
+    /// Set for tile objects, which are positioned by their bottom left corner.
+    #[serde(default)]
+    gid: Option<u32>,
+    x: f64,
+    y: f64,
+    #[serde(default)]
+    width: f64,
+    #[serde(default)]
+    height: f64,
+    #[serde(default)]
+    rotation: f64,
+    #[serde(default)]
+    ellipse: bool,
+    #[serde(default)]
+    point: bool,
+    #[serde(default)]
+    polygon: Option<Vec<Point>>,
+    #[serde(default)]
+    polyline: Option<Vec<Point>>,
+    #[serde(default)]
+    text: Option<Value>,
+    #[serde(default)]
+    properties: Vec<Property>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Tileset {
+    firstgid: u32,
+    #[serde(default)]
+    tiles: Vec<Tile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Tile {
+    id: u32,
+    #[serde(default)]
+    properties: Vec<Property>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Property {
+    name: String,
+    value: Value,
+}
+
+fn find_property<'a>(properties: &'a [Property], name: &str) -> Option<&'a Value> {
+    properties
+        .iter()
+        .find(|property| property.name == name)
+        .map(|property| &property.value)
+}
+
+fn bool_property(properties: &[Property], name: &str) -> Option<bool> {
+    find_property(properties, name).and_then(Value::as_bool)
+}
+
+fn int_property(properties: &[Property], name: &str) -> Option<i64> {
+    find_property(properties, name).and_then(Value::as_i64)
+}
+
+/// Parses a Tiled colour property, which is "#AARRGGBB" or "#RRGGBB", into RGBA.
+fn colour_property(properties: &[Property], name: &str) -> Option<[f32; 4]> {
+    let s = find_property(properties, name)?.as_str()?.trim_start_matches('#');
+    let (alpha, rgb) = match s.len() {
+        6 => ("ff", s),
+        8 => s.split_at(2),
+        _ => return None,
+    };
+    let channel = |hex: &str, i: usize| {
+        u8::from_str_radix(&hex[i..i + 2], 16)
+            .ok()
+            .map(|c| c as f32 / 255.)
+    };
+    Some([
+        channel(rgb, 0)?,
+        channel(rgb, 2)?,
+        channel(rgb, 4)?,
+        channel(alpha, 0)?,
+    ])
+}
+
+/// Custom properties recognised on objects and layers:
+/// - `player`, `pushable`, `sensor` (bool): entity flags
+/// - `mass`, `priority`, `layer` (int)
+/// - `colour` (color)
+///
+/// Each tile layer becomes a single tile map entity. A tile is solid if its tile has a
+/// `solid` bool property, and otherwise if its layer does.
+fn apply_properties(entity: &mut EntityDesc, properties: &[Property]) {
+    for &(name, flag) in [
+        ("player", Flag::Player),
+        ("pushable", Flag::Pushable),
+        ("sensor", Flag::Sensor),
+    ].iter()
+    {
+        if bool_property(properties, name) == Some(true) && !entity.has_flag(flag) {
+            entity.flags.push(flag);
+        }
+    }
+    if let Some(mass) = int_property(properties, "mass") {
+        entity.mass = Some(mass);
+    }
+    if let Some(priority) = int_property(properties, "priority") {
+        entity.priority = Some(priority as i32);
+    }
+    if let Some(colour) = colour_property(properties, "colour") {
+        entity.colour = [colour[0], colour[1], colour[2]];
+        entity.alpha = if colour[3] < 1. { Some(colour[3]) } else { None };
+    }
+    if let Some(layer) = int_property(properties, "layer") {
+        entity.layer = Some(layer as i32);
+    }
+}
+
+fn entity(position: [f64; 2], shape: ShapeDesc, colour: [f32; 3]) -> EntityDesc {
+    EntityDesc {
+        position,
+        shape,
+        colour,
+        alpha: None,
+        layer: None,
+        line_thickness: None,
+        outline: None,
+        velocity: None,
+        flags: Vec::new(),
+        mass: None,
+        priority: None,
+        path: None,
+    }
+}
+
+fn unsupported<T>(message: String) -> Result<T, LevelError> {
+    Err(LevelError::Invalid(message))
+}
+
+struct Importer<'a> {
+    map: &'a Map,
+    entities: Vec<EntityDesc>,
+}
+
+impl<'a> Importer<'a> {
+    fn tile_properties(&self, gid: u32) -> Option<&'a [Property]> {
+        let tileset = self.map
+            .tilesets
+            .iter()
+            .filter(|tileset| tileset.firstgid <= gid)
+            .max_by_key(|tileset| tileset.firstgid)?;
+        let id = gid - tileset.firstgid;
+        tileset
+            .tiles
+            .iter()
+            .find(|tile| tile.id == id)
+            .map(|tile| &tile.properties[..])
+    }
+    fn tile_layer(
+        &mut self,
+        name: &str,
+        width: u32,
+        height: u32,
+        data: &TileData,
+        properties: &[Property],
+        offset: [f64; 2],
+    ) -> Result<(), LevelError> {
+        let data = match data {
+            &TileData::Csv(ref data) => data,
+            &TileData::Encoded(_) => {
+                return unsupported(format!(
+                    "tile layer \"{}\" uses an encoded format; save the map with CSV layer data",
+                    name
+                ))
+            }
+        };
+        if data.len() != (width * height) as usize {
+            return unsupported(format!("tile layer \"{}\" has the wrong amount of data", name));
+        }
+        let layer_solid = bool_property(properties, "solid").unwrap_or(false);
+        let solid = data.iter()
+            .map(|&gid| {
+                let gid = gid & GID_MASK;
+                gid != 0 && self.tile_properties(gid)
+                    .and_then(|tile_properties| bool_property(tile_properties, "solid"))
+                    .unwrap_or(layer_solid)
+            })
+            .collect::<Vec<_>>();
+        if !solid.iter().any(|&solid| solid) {
+            return Ok(());
+        }
+        let rows = solid
+            .chunks(width as usize)
+            .map(|row| row.iter().map(|&solid| if solid { '#' } else { '.' }).collect())
+            .collect();
+        let mut tile_map = entity(
+            offset,
+            ShapeDesc::TileMap {
+                tile_size: [self.map.tilewidth as f64, self.map.tileheight as f64],
+                rows,
+            },
+            DEFAULT_TILE_COLOUR,
+        );
+        apply_properties(&mut tile_map, properties);
+        self.entities.push(tile_map);
+        Ok(())
+    }
+    fn object(
+        &mut self,
+        object: &Object,
+        layer_properties: &[Property],
+        offset: [f64; 2],
+    ) -> Result<(), LevelError> {
+        if object.point || object.text.is_some() {
+            return Ok(());
+        }
+        if object.rotation != 0. {
+            return unsupported(format!("object {} is rotated", object.id));
+        }
+        if object.ellipse {
+            return unsupported(format!("object {} is an ellipse", object.id));
+        }
+        let top = if object.gid.is_some() {
+            object.y - object.height
+        } else {
+            object.y
+        };
+        let position = [offset[0] + object.x, offset[1] + top];
+        let (points, closed) = match (&object.polygon, &object.polyline) {
+            (&Some(ref points), _) => (points, true),
+            (_, &Some(ref points)) => (points, false),
+            (&None, &None) => {
+                if object.width <= 0. || object.height <= 0. {
+                    return unsupported(format!("object {} has no area", object.id));
+                }
+                let mut rect = entity(
+                    position,
+                    ShapeDesc::AxisAlignedRect {
+                        size: [object.width, object.height],
+                    },
+                    DEFAULT_OBJECT_COLOUR,
+                );
+                apply_properties(&mut rect, layer_properties);
+                apply_properties(&mut rect, &object.properties);
+                self.entities.push(rect);
+                return Ok(());
+            }
+        };
+        let num_edges = if closed && points.len() > 2 {
+            points.len()
+        } else {
+            points.len().saturating_sub(1)
+        };
+        for i in 0..num_edges {
+            let start = &points[i];
+            let end = &points[(i + 1) % points.len()];
+            let mut edge = entity(
+                position,
+                ShapeDesc::LineSegment {
+                    start: [start.x, start.y],
+                    end: [end.x, end.y],
+                },
+                DEFAULT_OBJECT_COLOUR,
+            );
+            apply_properties(&mut edge, layer_properties);
+            apply_properties(&mut edge, &object.properties);
+            self.entities.push(edge);
+        }
+        Ok(())
+    }
+    /// Layer offsets add up through groups, starting from `offset`.
+    fn layers(&mut self, layers: &[Layer], offset: [f64; 2]) -> Result<(), LevelError> {
+        let add_offset = |x: f64, y: f64| [offset[0] + x, offset[1] + y];
+        for layer in layers {
+            match layer {
+                &Layer::TileLayer {
+                    ref name,
+                    width,
+                    height,
+                    ref data,
+                    ref properties,
+                    offsetx,
+                    offsety,
+                } => self.tile_layer(
+                    name,
+                    width,
+                    height,
+                    data,
+                    properties,
+                    add_offset(offsetx, offsety),
+                )?,
+                &Layer::ObjectGroup {
+                    ref objects,
+                    ref properties,
+                    offsetx,
+                    offsety,
+                } => for object in objects {
+                    self.object(object, properties, add_offset(offsetx, offsety))?;
+                },
+                &Layer::ImageLayer => (),
+                &Layer::Group {
+                    ref layers,
+                    offsetx,
+                    offsety,
+                } => self.layers(layers, add_offset(offsetx, offsety))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn convert(map: &Map) -> Result<Level, LevelError> {
+    if map.orientation != "orthogonal" {
+        return unsupported(format!("{} maps are not supported", map.orientation));
+    }
+    if map.infinite {
+        return unsupported("infinite maps are not supported".to_string());
+    }
+    let mut importer = Importer {
+        map,
+        entities: Vec::new(),
+    };
+    importer.layers(&map.layers, [0., 0.])?;
+    let level = Level {
+        size: [
+            (map.width * map.tilewidth) as f64,
+            (map.height * map.tileheight) as f64,
+        ],
+        entities: importer.entities,
+        camera_bounds: None,
+    };
+    level.validate()?;
+    Ok(level)
+}
+
+/// Imports a map saved in Tiled's JSON format with CSV tile layer data.
+pub fn import<R: Read>(reader: R) -> Result<Level, LevelError> {
+    let map: Map = serde_json::from_reader(reader)?;
+    convert(&map)
+}
+
+pub fn import_str(s: &str) -> Result<Level, LevelError> {
+    let map: Map = serde_json::from_str(s)?;
+    convert(&map)
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Level, LevelError> {
+    import(BufReader::new(File::open(path)?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rows(rows: &[&str]) -> Vec<String> {
+        rows.iter().map(|row| row.to_string()).collect()
+    }
+
+    #[test]
+    fn platforms() {
+        let level = import_str(include_str!("../levels/tiled/platforms.tmj")).unwrap();
+        assert_eq!(level.size, [256., 160.]);
+        assert_eq!(level.entities.len(), 4);
+        let ground = &level.entities[0];
+        assert_eq!(
+            ground.shape,
+            ShapeDesc::TileMap {
+                tile_size: [32., 32.],
+                rows: rows(&["........", "........", ".....##.", "........", "####.###"]),
+            }
+        );
+        let grey = 128. / 255.;
+        assert_eq!(ground.colour, [grey, grey, grey]);
+        let player = &level.entities[1];
+        assert_eq!(
+            (player.position, &player.shape),
+            ([32., 64.], &ShapeDesc::AxisAlignedRect { size: [32., 64.] })
+        );
+        assert_eq!(player.flags, vec![Flag::Player]);
+        assert_eq!(player.mass, Some(2));
+        assert_eq!(player.colour, [1., 0., 0.]);
+        assert_eq!(level.entities[2].flags, vec![Flag::Pushable]);
+        assert_eq!(level.entities[2].priority, Some(1));
+        assert_eq!(level.entities[3].flags, vec![Flag::Sensor]);
+    }
+
+    #[test]
+    fn polygons() {
+        let level = import_str(include_str!("../levels/tiled/polygons.tmj")).unwrap();
+        assert_eq!(level.size, [96., 64.]);
+        assert_eq!(level.entities.len(), 7);
+        assert_eq!(
+            level.entities[0].shape,
+            ShapeDesc::TileMap {
+                tile_size: [16., 16.],
+                rows: rows(&["#....#", "#....#", "#....#", "######"]),
+            }
+        );
+        let edges = level.entities[1..6]
+            .iter()
+            .map(|entity| match entity.shape {
+                ShapeDesc::LineSegment { start, end } => (entity.position, start, end),
+                ref other => panic!("unexpected shape: {:?}", other),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            edges,
+            vec![
+                ([16., 48.], [0., 0.], [32., 0.]),
+                ([16., 48.], [32., 0.], [32., -16.]),
+                ([16., 48.], [32., -16.], [0., 0.]),
+                ([48., 24.], [0., 0.], [16., 0.]),
+                ([48., 24.], [16., 0.], [24., 8.]),
+            ]
+        );
+        assert!(level.entities[1..6].iter().all(|entity| entity.colour == [0., 1., 0.]));
+        assert!(level.entities[6].has_flag(Flag::Player));
+    }
+
+    #[test]
+    fn offsets_and_tile_objects() {
+        let level = import_str(include_str!("../levels/tiled/offsets.tmj")).unwrap();
+        assert_eq!(level.entities.len(), 3);
+        let ground = &level.entities[0];
+        assert_eq!(ground.position, [8., 4.]);
+        // the flip flags, including the hexagonal rotation flag, don't change the tile
+        assert_eq!(
+            ground.shape,
+            ShapeDesc::TileMap {
+                tile_size: [16., 16.],
+                rows: rows(&["....", "....", "....", "####"]),
+            }
+        );
+        let player = &level.entities[1];
+        assert!(player.has_flag(Flag::Player));
+        assert_eq!(
+            (player.position, &player.shape),
+            ([16., 32.], &ShapeDesc::AxisAlignedRect { size: [16., 16.] })
+        );
+        assert_eq!(level.entities[2].position, [41., 32.]);
+    }
+
+    #[test]
+    fn rejects_encoded_layers() {
+        let map = include_str!("../levels/tiled/polygons.tmj").replace(
+            "\"data\": [\n        1, 0, 0, 0, 0, 1,\n        1, 0, 0, 0, 0, 1,\n        1, 0, 0, 0, 0, 1,\n        1, 1, 1, 1, 1, 1\n      ]",
+            "\"encoding\": \"base64\", \"data\": \"AQAAAA==\"",
+        );
+        match import_str(&map) {
+            Err(LevelError::Invalid(_)) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! A minimal 2D vector which builds without std.
+
+use num::Zero;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Vector2<T> {
+    pub x: T,
+    pub y: T,
+}
+
+pub fn vec2<T>(x: T, y: T) -> Vector2<T> {
+    Vector2 { x, y }
+}
+
+impl<T> Vector2<T> {
+    pub fn new(x: T, y: T) -> Self {
+        Self { x, y }
+    }
+}
+
+impl<T: Copy + Add<Output = T> + Mul<Output = T>> Vector2<T> {
+    pub fn dot(self, other: Self) -> T {
+        self.x * other.x + self.y * other.y
+    }
+    pub fn magnitude2(self) -> T {
+        self.dot(self)
+    }
+    pub fn mul_element_wise(self, other: Self) -> Self {
+        vec2(self.x * other.x, self.y * other.y)
+    }
+}
+
+impl<T: Copy + Div<Output = T>> Vector2<T> {
+    pub fn div_element_wise(self, other: Self) -> Self {
+        vec2(self.x / other.x, self.y / other.y)
+    }
+}
+
+impl Vector2<f32> {
+    pub fn magnitude(self) -> f32 {
+        ::libm::sqrtf(self.magnitude2())
+    }
+    pub fn normalize(self) -> Self {
+        self / self.magnitude()
+    }
+    /// The component of this vector in the direction of `other`.
+    pub fn project_on(self, other: Self) -> Self {
+        other * (self.dot(other) / other.magnitude2())
+    }
+}
+
+impl<T: Zero> Zero for Vector2<T> {
+    fn zero() -> Self {
+        vec2(T::zero(), T::zero())
+    }
+    fn is_zero(&self) -> bool {
+        self.x.is_zero() && self.y.is_zero()
+    }
+}
+
+impl<T: Copy> From<[T; 2]> for Vector2<T> {
+    fn from(v: [T; 2]) -> Self {
+        vec2(v[0], v[1])
+    }
+}
+
+impl<T> From<Vector2<T>> for [T; 2] {
+    fn from(v: Vector2<T>) -> Self {
+        [v.x, v.y]
+    }
+}
+
+impl<T: Add<Output = T>> Add for Vector2<T> {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        vec2(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl<T: Sub<Output = T>> Sub for Vector2<T> {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        vec2(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl<T: Neg<Output = T>> Neg for Vector2<T> {
+    type Output = Self;
+    fn neg(self) -> Self {
+        vec2(-self.x, -self.y)
+    }
+}
+
+impl<T: Copy + Mul<Output = T>> Mul<T> for Vector2<T> {
+    type Output = Self;
+    fn mul(self, scalar: T) -> Self {
+        vec2(self.x * scalar, self.y * scalar)
+    }
+}
+
+impl<T: Copy + Div<Output = T>> Div<T> for Vector2<T> {
+    type Output = Self;
+    fn div(self, scalar: T) -> Self {
+        vec2(self.x / scalar, self.y / scalar)
+    }
+}
+
+impl<T: AddAssign> AddAssign for Vector2<T> {
+    fn add_assign(&mut self, other: Self) {
+        self.x += other.x;
+        self.y += other.y;
+    }
+}
+
+impl<T: SubAssign> SubAssign for Vector2<T> {
+    fn sub_assign(&mut self, other: Self) {
+        self.x -= other.x;
+        self.y -= other.y;
+    }
+}
+
+impl<T: Copy + MulAssign> MulAssign<T> for Vector2<T> {
+    fn mul_assign(&mut self, scalar: T) {
+        self.x *= scalar;
+        self.y *= scalar;
+    }
+}
+
+impl<T: Copy + DivAssign> DivAssign<T> for Vector2<T> {
+    fn div_assign(&mut self, scalar: T) {
+        self.x /= scalar;
+        self.y /= scalar;
+    }
+}
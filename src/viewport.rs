@@ -0,0 +1,136 @@
+use vector::{Vector2, vec2};
+use std::str::FromStr;
+
+/// How the world is fitted to a window which isn't the same size as it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scaling {
+    /// Fill the window, distorting the world if the aspect ratios differ.
+    Stretch,
+    /// Scale by the largest whole number which fits (at least 1), and centre the world
+    /// with black bars around it.
+    IntegerLetterbox,
+    /// Keep world pixels the same size as window pixels, and show more or less of the
+    /// world.
+    ShowMore,
+}
+
+impl Default for Scaling {
+    fn default() -> Self {
+        Scaling::IntegerLetterbox
+    }
+}
+
+impl Scaling {
+    /// How much of the world fits in the window, given how much would fit in a window of
+    /// `world_size`.
+    pub fn visible_size(
+        self,
+        world_size: Vector2<f32>,
+        window_size: Vector2<f32>,
+    ) -> Vector2<f32> {
+        match self {
+            Scaling::ShowMore => window_size,
+            Scaling::Stretch | Scaling::IntegerLetterbox => world_size,
+        }
+    }
+}
+
+impl FromStr for Scaling {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stretch" => Ok(Scaling::Stretch),
+            "letterbox" => Ok(Scaling::IntegerLetterbox),
+            "show-more" => Ok(Scaling::ShowMore),
+            other => Err(format!(
+                "Unknown scaling: {} (expected stretch, letterbox or show-more)",
+                other
+            )),
+        }
+    }
+}
+
+/// Maps world pixels to window pixels: `window = world * scale + offset`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub scale: Vector2<f32>,
+    pub offset: Vector2<f32>,
+    /// The top left and size, in window pixels, of the part of the window which can be
+    /// drawn on. Everything can be if there's no clip.
+    pub clip: Option<(Vector2<f32>, Vector2<f32>)>,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self {
+            scale: vec2(1., 1.),
+            offset: vec2(0., 0.),
+            clip: None,
+        }
+    }
+}
+
+impl Viewport {
+    pub fn new(
+        scaling: Scaling,
+        world_size: Vector2<f32>,
+        window_size: Vector2<f32>,
+    ) -> Self {
+        match scaling {
+            Scaling::Stretch => Self {
+                scale: window_size.div_element_wise(world_size),
+                offset: vec2(0., 0.),
+                clip: None,
+            },
+            Scaling::IntegerLetterbox => {
+                let ratio = window_size.div_element_wise(world_size);
+                let scale = ratio.x.min(ratio.y).floor().max(1.);
+                // whole pixel offsets keep edges sharp
+                let offset = (window_size - world_size * scale) / 2.;
+                let offset = vec2(offset.x.floor(), offset.y.floor());
+                Self {
+                    scale: vec2(scale, scale),
+                    offset,
+                    // the bars stay black even when the camera could show more
+                    clip: Some((offset, world_size * scale)),
+                }
+            }
+            Scaling::ShowMore => Default::default(),
+        }
+    }
+    pub fn world_to_window(&self, v: Vector2<f32>) -> Vector2<f32> {
+        v.mul_element_wise(self.scale) + self.offset
+    }
+    pub fn window_to_world(&self, v: Vector2<f32>) -> Vector2<f32> {
+        (v - self.offset).div_element_wise(self.scale)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scaling() {
+        let world = vec2(960., 640.);
+        let exact = Viewport::new(Scaling::IntegerLetterbox, world, world);
+        assert_eq!((exact.scale, exact.offset), (vec2(1., 1.), vec2(0., 0.)));
+        let stretched = Viewport::new(Scaling::Stretch, world, vec2(1920., 960.));
+        assert_eq!(stretched.scale, vec2(2., 1.5));
+        assert_eq!(stretched.world_to_window(world), vec2(1920., 960.));
+        let letterboxed = Viewport::new(Scaling::IntegerLetterbox, world, vec2(2000., 1400.));
+        assert_eq!(letterboxed.scale, vec2(2., 2.));
+        assert_eq!(letterboxed.offset, vec2(40., 60.));
+        assert_eq!(letterboxed.window_to_world(vec2(40., 60.)), vec2(0., 0.));
+        assert_eq!(letterboxed.clip, Some((vec2(40., 60.), vec2(1920., 1280.))));
+        let small = Viewport::new(Scaling::IntegerLetterbox, world, vec2(800., 640.));
+        assert_eq!(small.scale, vec2(1., 1.));
+        assert_eq!(small.offset, vec2(-80., 0.));
+        let more = Viewport::new(Scaling::ShowMore, world, vec2(1920., 960.));
+        assert_eq!(more, Default::default());
+        let window = vec2(1920., 960.);
+        assert_eq!(Scaling::ShowMore.visible_size(world, window), window);
+        assert_eq!(Scaling::IntegerLetterbox.visible_size(world, window), world);
+        assert_eq!("show-more".parse(), Ok(Scaling::ShowMore));
+    }
+}